@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+use crate::{Error, Item};
+
+/// Maps input characters to item priorities.
+pub trait Alphabet {
+    /// Largest priority this alphabet produces, `None` if unbounded.
+    fn max_priority(&self) -> Option<u32>;
+    fn item(&self, c: char) -> Result<Item, Error>;
+    fn symbol(&self, item: Item) -> Option<char>;
+}
+
+/// The puzzle's alphabet: `a-z` are 1 through 26, `A-Z` are 27 through 52.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Letters;
+
+impl Alphabet for Letters {
+    fn max_priority(&self) -> Option<u32> {
+        Some(52)
+    }
+
+    fn item(&self, c: char) -> Result<Item, Error> {
+        match c {
+            'a'..='z' => Item::from_priority(c as u32 - 'a' as u32 + 1),
+            'A'..='Z' => Item::from_priority(c as u32 - 'A' as u32 + 27),
+            _ => Err(Error::InvalidItem),
+        }
+    }
+
+    fn symbol(&self, item: Item) -> Option<char> {
+        match item.priority() {
+            p @ 1..=26 => char::from_u32('a' as u32 + p - 1),
+            p @ 27..=52 => char::from_u32('A' as u32 + p - 27),
+            _ => None,
+        }
+    }
+}
+
+/// [`Letters`], then digits as 53 through 62, then the remaining printable ASCII punctuation
+/// in code point order as 63 through 94.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ascii;
+
+impl Ascii {
+    fn punctuation() -> impl Iterator<Item = char> {
+        ('!'..='~').filter(|c| c.is_ascii_punctuation())
+    }
+}
+
+impl Alphabet for Ascii {
+    fn max_priority(&self) -> Option<u32> {
+        Some(94)
+    }
+
+    fn item(&self, c: char) -> Result<Item, Error> {
+        match c {
+            'a'..='z' | 'A'..='Z' => Letters.item(c),
+            '0'..='9' => Item::from_priority(c as u32 - '0' as u32 + 53),
+            _ => match Self::punctuation().position(|p| p == c) {
+                Some(i) => Item::from_priority(i as u32 + 63),
+                None => Err(Error::InvalidItem),
+            },
+        }
+    }
+
+    fn symbol(&self, item: Item) -> Option<char> {
+        match item.priority() {
+            1..=52 => Letters.symbol(item),
+            p @ 53..=62 => char::from_u32('0' as u32 + p - 53),
+            p @ 63..=94 => Self::punctuation().nth(p as usize - 63),
+            _ => None,
+        }
+    }
+}
+
+/// Any character, with priority one more than its code point.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unicode;
+
+impl Alphabet for Unicode {
+    fn max_priority(&self) -> Option<u32> {
+        None
+    }
+
+    fn item(&self, c: char) -> Result<Item, Error> {
+        Item::from_priority(c as u32 + 1)
+    }
+
+    fn symbol(&self, item: Item) -> Option<char> {
+        char::from_u32(item.priority() - 1)
+    }
+}
+
+/// User supplied priorities, parsed from lines of `<char> <priority>`.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    items: HashMap<char, Item>,
+    symbols: BTreeMap<Item, char>,
+}
+
+impl Table {
+    /// Each character is given its 1-based position as priority.
+    pub fn ordered(chars: &str) -> Result<Self, Error> {
+        let mut table = Table::default();
+        for (i, c) in chars.chars().enumerate() {
+            table.insert(c, Item::from_priority(i as u32 + 1)?)?;
+        }
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, c: char, item: Item) -> Result<(), Error> {
+        if self.items.insert(c, item).is_some() {
+            return Err(Error::InvalidTable);
+        }
+        self.symbols.entry(item).or_insert(c);
+        Ok(())
+    }
+}
+
+impl FromStr for Table {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table = Table::default();
+        for line in s.lines().filter(|l| !l.trim().is_empty()) {
+            let mut chars = line.chars();
+            let (Some(c), Some(' ')) = (chars.next(), chars.next()) else {
+                return Err(Error::InvalidTable);
+            };
+            let priority = chars
+                .as_str()
+                .trim()
+                .parse()
+                .map_err(|_| Error::InvalidTable)?;
+            table.insert(c, Item::from_priority(priority)?)?;
+        }
+        Ok(table)
+    }
+}
+
+impl Alphabet for Table {
+    fn max_priority(&self) -> Option<u32> {
+        Some(self.symbols.keys().last().map_or(0, |i| i.priority()))
+    }
+
+    fn item(&self, c: char) -> Result<Item, Error> {
+        self.items.get(&c).copied().ok_or(Error::InvalidItem)
+    }
+
+    fn symbol(&self, item: Item) -> Option<char> {
+        self.symbols.get(&item).copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Alphabet, Ascii, Error, Letters, Table, Unicode};
+
+    fn round_trip(alphabet: &impl Alphabet, chars: impl Iterator<Item = char>) {
+        for c in chars {
+            let item = alphabet.item(c).expect("valid item");
+            assert_eq!(alphabet.symbol(item), Some(c));
+        }
+    }
+
+    #[test]
+    fn alphabets() {
+        round_trip(&Letters, ('a'..='z').chain('A'..='Z'));
+        round_trip(&Ascii, '!'..='~');
+        round_trip(&Unicode, "añ€😀".chars());
+        assert_eq!(Ascii.item('0').unwrap().priority(), 53);
+        assert_eq!(Ascii.item('~').unwrap().priority(), 94);
+        assert!(Ascii.item(' ').is_err());
+        assert!(Letters.item('0').is_err());
+    }
+
+    #[test]
+    fn tables() {
+        let table: Table = "x 5\n# 300\n\ny 5\n".parse().expect("table");
+        assert_eq!(table.item('#').unwrap().priority(), 300);
+        assert_eq!(table.item('y').unwrap().priority(), 5);
+        assert_eq!(table.max_priority(), Some(300));
+        assert!(table.item('z').is_err());
+        assert_eq!("xx 1".parse::<Table>().err(), Some(Error::InvalidTable));
+        assert_eq!("x 1\nx 2".parse::<Table>().err(), Some(Error::InvalidTable));
+
+        let ordered = Table::ordered("zyx").expect("table");
+        assert_eq!(ordered.item('x').unwrap().priority(), 3);
+        round_trip(&ordered, "zyx".chars());
+    }
+}
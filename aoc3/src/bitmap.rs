@@ -0,0 +1,264 @@
+use std::{
+    fmt::Display,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub, SubAssign},
+};
+
+use crate::{Bits, Error, Item};
+
+/// Presence set of items, bit `priority - 1` is set when the item is present.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BitmapSet<B: Bits = u64>(B);
+
+impl BitmapSet<u64> {
+    /// Every item of the puzzle's 52 letter alphabet.
+    pub fn all() -> Self {
+        Self::universe(52)
+    }
+}
+
+impl<B: Bits> BitmapSet<B> {
+    pub fn new() -> Self {
+        BitmapSet(B::zero())
+    }
+
+    pub fn from_bits(bits: B) -> Self {
+        BitmapSet(bits)
+    }
+
+    pub fn bits(&self) -> &B {
+        &self.0
+    }
+
+    /// Every item with a priority from 1 to `size`, truncated to the capacity of `B`.
+    pub fn universe(size: u32) -> Self {
+        let mut set = Self::new();
+        let size = B::CAPACITY.map_or(size as usize, |c| c.min(size as usize));
+        (0..size).for_each(|bit| {
+            set.0.set(bit);
+        });
+        set
+    }
+
+    fn bit(item: Item) -> usize {
+        item.priority() as usize - 1
+    }
+
+    /// Panics if the item does not fit in `B`, see [`BitmapSet::try_insert`].
+    pub fn insert(&mut self, item: Item) {
+        self.try_insert(item).expect("Item beyond set capacity")
+    }
+
+    pub fn try_insert(&mut self, item: Item) -> Result<(), Error> {
+        match self.0.set(Self::bit(item)) {
+            true => Ok(()),
+            false => Err(Error::InvalidItem),
+        }
+    }
+
+    pub fn remove(&mut self, item: Item) {
+        self.0.clear(Self::bit(item))
+    }
+
+    pub fn contains(&self, item: Item) -> bool {
+        self.0.get(Self::bit(item))
+    }
+
+    pub fn union(&self, rhs: &Self) -> Self {
+        let mut result = self.clone();
+        result.0.or(&rhs.0);
+        result
+    }
+
+    pub fn intersect(&self, rhs: &Self) -> Self {
+        let mut result = self.clone();
+        result.0.and(&rhs.0);
+        result
+    }
+
+    pub fn difference(&self, rhs: &Self) -> Self {
+        let mut result = self.clone();
+        result.0.and_not(&rhs.0);
+        result
+    }
+
+    pub fn symmetric_difference(&self, rhs: &Self) -> Self {
+        let mut result = self.clone();
+        result.0.xor(&rhs.0);
+        result
+    }
+
+    /// Items with a priority from 1 to `size` that are not in this set.
+    pub fn complement(&self, size: u32) -> Self {
+        Self::universe(size).difference(self)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn iter(&self) -> Iter<'_, B> {
+        Iter {
+            bits: &self.0,
+            next: 0,
+        }
+    }
+}
+
+impl<B: Bits> Default for BitmapSet<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Yields the items of a [`BitmapSet`] in ascending priority order.
+#[derive(Debug, Clone)]
+pub struct Iter<'a, B: Bits> {
+    bits: &'a B,
+    next: usize,
+}
+
+impl<B: Bits> Iterator for Iter<'_, B> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bit = self.bits.next_set(self.next)?;
+        self.next = bit + 1;
+        Item::from_priority(bit as u32 + 1).ok()
+    }
+}
+
+impl<'a, B: Bits> IntoIterator for &'a BitmapSet<B> {
+    type Item = Item;
+    type IntoIter = Iter<'a, B>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<B: Bits> FromIterator<Item> for BitmapSet<B> {
+    fn from_iter<T: IntoIterator<Item = Item>>(iter: T) -> Self {
+        let mut set = BitmapSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<B: Bits> Extend<Item> for BitmapSet<B> {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, iter: T) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<B: Bits> Display for BitmapSet<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{item}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+macro_rules! set_operator {
+    ($op:ident, $fn:ident, $assign:ident, $assign_fn:ident, $method:ident) => {
+        impl<B: Bits> $op for BitmapSet<B> {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self::Output {
+                self.$method(&rhs)
+            }
+        }
+
+        impl<B: Bits> $assign for BitmapSet<B> {
+            fn $assign_fn(&mut self, rhs: Self) {
+                *self = self.$method(&rhs)
+            }
+        }
+    };
+}
+
+set_operator!(BitOr, bitor, BitOrAssign, bitor_assign, union);
+set_operator!(BitAnd, bitand, BitAndAssign, bitand_assign, intersect);
+set_operator!(
+    BitXor,
+    bitxor,
+    BitXorAssign,
+    bitxor_assign,
+    symmetric_difference
+);
+set_operator!(Sub, sub, SubAssign, sub_assign, difference);
+
+/// Complement relative to the puzzle's 52 letters, wider sets use [`BitmapSet::complement`].
+impl Not for BitmapSet<u64> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        self.complement(52)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BitmapSet, DynamicBits, Item};
+
+    fn set(s: &str) -> BitmapSet {
+        s.chars().map(|c| Item::try_from(c).unwrap()).collect()
+    }
+
+    #[test]
+    fn operations() {
+        let l = set("abcZ");
+        let r = set("cdZ");
+        assert_eq!(l | r, set("abcdZ"));
+        assert_eq!(l & r, set("cZ"));
+        assert_eq!(l - r, set("ab"));
+        assert_eq!(l ^ r, set("abd"));
+        assert_eq!((!l).len(), 48);
+        assert_eq!(!BitmapSet::all(), BitmapSet::new());
+
+        let mut s = l;
+        s.remove(Item::try_from('Z').unwrap());
+        assert_eq!(s, set("abc"));
+        assert_eq!(s.len(), 3);
+        assert!(!s.is_empty());
+        assert!(BitmapSet::<u64>::new().is_empty());
+    }
+
+    #[test]
+    fn iter_and_display() {
+        let s = set("Zpa");
+        let chars: String = s.iter().filter_map(|i| i.to_char()).collect();
+        assert_eq!(chars, "apZ");
+        assert_eq!(s.to_string(), "{a, p, Z}");
+        assert!(s.contains(Item::try_from('Z').unwrap()));
+        assert!(!s.contains(Item::try_from('z').unwrap()));
+    }
+
+    #[test]
+    fn wide_sets() {
+        let high = Item::from_priority(100).unwrap();
+        let mut narrow = BitmapSet::<u64>::new();
+        assert!(narrow.try_insert(high).is_err());
+
+        let mut wide = BitmapSet::<u128>::new();
+        wide.insert(high);
+        assert!(wide.contains(high));
+        assert_eq!(wide.complement(100).len(), 99);
+
+        let huge = Item::from_priority(0x1F600).unwrap();
+        let dynamic: BitmapSet<DynamicBits> = [high, huge].into_iter().collect();
+        let items: Vec<_> = dynamic.iter().collect();
+        assert_eq!(items, vec![high, huge]);
+        assert_eq!(dynamic.clone() - dynamic, BitmapSet::new());
+    }
+}
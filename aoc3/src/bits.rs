@@ -0,0 +1,284 @@
+use std::{fmt::Debug, hash::Hash};
+
+/// Storage behind a [`crate::BitmapSet`].
+pub trait Bits: Clone + PartialEq + Eq + Hash + Debug {
+    /// Bits available, or `None` if the storage grows as needed.
+    const CAPACITY: Option<usize>;
+
+    fn zero() -> Self;
+    fn get(&self, bit: usize) -> bool;
+    /// Sets `bit`, returning `false` if it is beyond the capacity.
+    fn set(&mut self, bit: usize) -> bool;
+    fn clear(&mut self, bit: usize);
+    fn count(&self) -> usize;
+    fn is_zero(&self) -> bool;
+    /// Lowest set bit at or above `from`.
+    fn next_set(&self, from: usize) -> Option<usize>;
+    fn and(&mut self, rhs: &Self);
+    fn or(&mut self, rhs: &Self);
+    fn xor(&mut self, rhs: &Self);
+    fn and_not(&mut self, rhs: &Self);
+}
+
+macro_rules! primitive_bits {
+    ($($t:ty),*) => {$(
+        impl Bits for $t {
+            const CAPACITY: Option<usize> = Some(<$t>::BITS as usize);
+
+            fn zero() -> Self {
+                0
+            }
+
+            fn get(&self, bit: usize) -> bool {
+                bit < <$t>::BITS as usize && (self >> bit) & 1 != 0
+            }
+
+            fn set(&mut self, bit: usize) -> bool {
+                if bit >= <$t>::BITS as usize {
+                    return false;
+                }
+                *self |= 1 << bit;
+                true
+            }
+
+            fn clear(&mut self, bit: usize) {
+                if bit < <$t>::BITS as usize {
+                    *self &= !(1 << bit);
+                }
+            }
+
+            fn count(&self) -> usize {
+                self.count_ones() as usize
+            }
+
+            fn is_zero(&self) -> bool {
+                *self == 0
+            }
+
+            fn next_set(&self, from: usize) -> Option<usize> {
+                if from >= <$t>::BITS as usize {
+                    return None;
+                }
+                match self >> from {
+                    0 => None,
+                    rest => Some(from + rest.trailing_zeros() as usize),
+                }
+            }
+
+            fn and(&mut self, rhs: &Self) {
+                *self &= rhs
+            }
+
+            fn or(&mut self, rhs: &Self) {
+                *self |= rhs
+            }
+
+            fn xor(&mut self, rhs: &Self) {
+                *self ^= rhs
+            }
+
+            fn and_not(&mut self, rhs: &Self) {
+                *self &= !rhs
+            }
+        }
+    )*};
+}
+
+primitive_bits!(u64, u128);
+
+fn words_get(words: &[u64], bit: usize) -> bool {
+    words
+        .get(bit / 64)
+        .is_some_and(|w| (w >> (bit % 64)) & 1 != 0)
+}
+
+fn words_next_set(words: &[u64], from: usize) -> Option<usize> {
+    let mut index = from / 64;
+    let mut word = *words.get(index)? & (u64::MAX << (from % 64));
+    loop {
+        if word != 0 {
+            return Some(index * 64 + word.trailing_zeros() as usize);
+        }
+        index += 1;
+        word = *words.get(index)?;
+    }
+}
+
+impl<const N: usize> Bits for [u64; N] {
+    const CAPACITY: Option<usize> = Some(N * 64);
+
+    fn zero() -> Self {
+        [0; N]
+    }
+
+    fn get(&self, bit: usize) -> bool {
+        words_get(self, bit)
+    }
+
+    fn set(&mut self, bit: usize) -> bool {
+        match self.get_mut(bit / 64) {
+            Some(word) => {
+                *word |= 1 << (bit % 64);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear(&mut self, bit: usize) {
+        if let Some(word) = self.get_mut(bit / 64) {
+            *word &= !(1 << (bit % 64));
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.iter().all(|&w| w == 0)
+    }
+
+    fn next_set(&self, from: usize) -> Option<usize> {
+        words_next_set(self, from)
+    }
+
+    fn and(&mut self, rhs: &Self) {
+        self.iter_mut().zip(rhs).for_each(|(l, r)| *l &= r)
+    }
+
+    fn or(&mut self, rhs: &Self) {
+        self.iter_mut().zip(rhs).for_each(|(l, r)| *l |= r)
+    }
+
+    fn xor(&mut self, rhs: &Self) {
+        self.iter_mut().zip(rhs).for_each(|(l, r)| *l ^= r)
+    }
+
+    fn and_not(&mut self, rhs: &Self) {
+        self.iter_mut().zip(rhs).for_each(|(l, r)| *l &= !r)
+    }
+}
+
+/// Growable bitvector, trailing zero words are always trimmed so equal sets compare equal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DynamicBits(Vec<u64>);
+
+impl DynamicBits {
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+}
+
+impl Bits for DynamicBits {
+    const CAPACITY: Option<usize> = None;
+
+    fn zero() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, bit: usize) -> bool {
+        words_get(&self.0, bit)
+    }
+
+    fn set(&mut self, bit: usize) -> bool {
+        let index = bit / 64;
+        if index >= self.0.len() {
+            self.0.resize(index + 1, 0);
+        }
+        self.0[index] |= 1 << (bit % 64);
+        true
+    }
+
+    fn clear(&mut self, bit: usize) {
+        if let Some(word) = self.0.get_mut(bit / 64) {
+            *word &= !(1 << (bit % 64));
+            self.trim();
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn next_set(&self, from: usize) -> Option<usize> {
+        words_next_set(&self.0, from)
+    }
+
+    fn and(&mut self, rhs: &Self) {
+        self.0.truncate(rhs.0.len());
+        self.0.iter_mut().zip(&rhs.0).for_each(|(l, r)| *l &= r);
+        self.trim();
+    }
+
+    fn or(&mut self, rhs: &Self) {
+        if self.0.len() < rhs.0.len() {
+            self.0.resize(rhs.0.len(), 0);
+        }
+        self.0.iter_mut().zip(&rhs.0).for_each(|(l, r)| *l |= r);
+    }
+
+    fn xor(&mut self, rhs: &Self) {
+        if self.0.len() < rhs.0.len() {
+            self.0.resize(rhs.0.len(), 0);
+        }
+        self.0.iter_mut().zip(&rhs.0).for_each(|(l, r)| *l ^= r);
+        self.trim();
+    }
+
+    fn and_not(&mut self, rhs: &Self) {
+        self.0.iter_mut().zip(&rhs.0).for_each(|(l, r)| *l &= !r);
+        self.trim();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bits::{Bits, DynamicBits};
+
+    fn exercise<B: Bits>(high: usize) {
+        let mut l = B::zero();
+        assert!(l.set(3));
+        assert!(l.set(high));
+        let mut r = B::zero();
+        assert!(r.set(high));
+        assert!(r.set(5));
+        assert_eq!(l.next_set(0), Some(3));
+        assert_eq!(l.next_set(4), Some(high));
+        assert_eq!(l.next_set(high + 1), None);
+
+        let mut both = l.clone();
+        both.and(&r);
+        assert_eq!(both.count(), 1);
+        assert!(both.get(high));
+
+        let mut either = l.clone();
+        either.xor(&r);
+        assert_eq!(either.count(), 2);
+        assert!(!either.get(high));
+
+        let mut only = l.clone();
+        only.and_not(&r);
+        only.clear(3);
+        assert!(only.is_zero());
+        assert_eq!(only, B::zero());
+    }
+
+    #[test]
+    fn backings() {
+        exercise::<u64>(63);
+        exercise::<u128>(127);
+        exercise::<[u64; 4]>(200);
+        exercise::<DynamicBits>(10_000);
+        let mut narrow = 0u64;
+        assert!(!narrow.set(64));
+        let mut words = [0u64; 2];
+        assert!(!words.set(128));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{BitmapSet, Bits, Item};
+
+/// Multiset of items, one counter per priority.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ItemCounts(Vec<u32>);
+
+impl ItemCounts {
+    pub fn new() -> Self {
+        ItemCounts(vec![])
+    }
+
+    fn index(item: Item) -> usize {
+        item.priority() as usize - 1
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    pub fn get(&self, item: Item) -> u32 {
+        self.0.get(Self::index(item)).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, item: Item, count: u32) {
+        let index = Self::index(item);
+        if index >= self.0.len() {
+            self.0.resize(index + 1, 0);
+        }
+        self.0[index] += count;
+        self.trim();
+    }
+
+    /// Removes up to `count` of the item, returning how many were actually removed.
+    pub fn take(&mut self, item: Item, count: u32) -> u32 {
+        let Some(current) = self.0.get_mut(Self::index(item)) else {
+            return 0;
+        };
+        let taken = count.min(*current);
+        *current -= taken;
+        self.trim();
+        taken
+    }
+
+    pub fn total(&self) -> u32 {
+        self.0.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Items with a non-zero count in ascending priority order.
+    pub fn iter(&self) -> impl Iterator<Item = (Item, u32)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .filter_map(|(i, &count)| Some((Item::from_priority(i as u32 + 1).ok()?, count)))
+    }
+
+    /// Sum of each item's priority times its count.
+    pub fn weighted_priority(&self) -> u64 {
+        self.iter()
+            .map(|(item, count)| item.priority() as u64 * count as u64)
+            .sum()
+    }
+
+    /// The item with the highest count, ties going to the higher priority.
+    pub fn most_common(&self) -> Option<(Item, u32)> {
+        self.iter().max_by_key(|&(item, count)| (count, item))
+    }
+
+    /// Counts of both multisets added together.
+    pub fn sum(&self, rhs: &Self) -> Self {
+        let mut result = self.clone();
+        rhs.iter().for_each(|(item, count)| result.add(item, count));
+        result
+    }
+
+    /// Multiset intersection, the smaller of the two counts for each item.
+    pub fn min(&self, rhs: &Self) -> Self {
+        self.iter()
+            .map(|(item, count)| (item, count.min(rhs.get(item))))
+            .collect()
+    }
+
+    /// Only the items also in `set`, letting a bitmap do the filtering first.
+    pub fn restrict<B: Bits>(&self, set: &BitmapSet<B>) -> Self {
+        set.iter().map(|item| (item, self.get(item))).collect()
+    }
+
+    /// Presence only view of the counts.
+    pub fn set<B: Bits>(&self) -> BitmapSet<B> {
+        self.iter().map(|(item, _)| item).collect()
+    }
+}
+
+impl FromIterator<Item> for ItemCounts {
+    fn from_iter<T: IntoIterator<Item = Item>>(iter: T) -> Self {
+        let mut counts = ItemCounts::new();
+        counts.extend(iter);
+        counts
+    }
+}
+
+impl FromIterator<(Item, u32)> for ItemCounts {
+    fn from_iter<T: IntoIterator<Item = (Item, u32)>>(iter: T) -> Self {
+        let mut counts = ItemCounts::new();
+        for (item, count) in iter {
+            counts.add(item, count);
+        }
+        counts
+    }
+}
+
+impl Extend<Item> for ItemCounts {
+    fn extend<T: IntoIterator<Item = Item>>(&mut self, iter: T) {
+        for item in iter {
+            self.add(item, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{BitmapSet, Item, ItemCounts};
+
+    #[test]
+    fn counting() {
+        let item = |c| Item::try_from(c).unwrap();
+        let mut counts: ItemCounts = "abbZZZ".chars().map(item).collect();
+        assert_eq!(counts.get(item('Z')), 3);
+        assert_eq!(counts.get(item('q')), 0);
+        assert_eq!(counts.total(), 6);
+        assert_eq!(counts.take(item('Z'), 5), 3);
+        assert_eq!(counts.take(item('q'), 1), 0);
+        assert_eq!(counts, "bab".chars().map(item).collect());
+        let set: BitmapSet = counts.set();
+        assert_eq!(set.to_string(), "{a, b}");
+        assert_eq!(
+            counts.iter().collect::<Vec<_>>(),
+            vec![(item('a'), 1), (item('b'), 2)]
+        );
+    }
+
+    #[test]
+    fn queries() {
+        let item = |c| Item::try_from(c).unwrap();
+        let l: ItemCounts = "aabZZZ".chars().map(item).collect();
+        let r: ItemCounts = "abbbc".chars().map(item).collect();
+        assert_eq!(l.weighted_priority(), 2 + 2 + 3 * 52);
+        assert_eq!(l.most_common(), Some((item('Z'), 3)));
+        assert_eq!(r.most_common(), Some((item('b'), 3)));
+        assert_eq!(l.min(&r), "ab".chars().map(item).collect());
+        assert_eq!(l.sum(&r).get(item('b')), 4);
+        let set: BitmapSet = "bcq".chars().map(item).collect();
+        assert_eq!(r.restrict(&set), "bbbc".chars().map(item).collect());
+        assert_eq!(ItemCounts::new().most_common(), None);
+    }
+}
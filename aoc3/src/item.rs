@@ -0,0 +1,71 @@
+use std::fmt::Display;
+
+use crate::{Alphabet, BitmapSet, Bits, Error, Letters};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Item(u32);
+
+impl Item {
+    pub fn from_priority(priority: u32) -> Result<Self, Error> {
+        match priority {
+            0 => Err(Error::InvalidItem),
+            _ => Ok(Item(priority)),
+        }
+    }
+
+    pub fn priority(self) -> u32 {
+        self.0
+    }
+
+    /// The item's letter in the puzzle's alphabet, if it has one.
+    pub fn to_char(self) -> Option<char> {
+        Letters.symbol(self)
+    }
+}
+
+impl TryFrom<char> for Item {
+    type Error = Error;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        Letters.item(c)
+    }
+}
+
+/// Succeeds only when exactly one item is in the set.
+impl<B: Bits> TryFrom<BitmapSet<B>> for Item {
+    type Error = Error;
+
+    fn try_from(value: BitmapSet<B>) -> Result<Self, Self::Error> {
+        if value.len() != 1 {
+            return Err(Error::NoMatch);
+        }
+        value.iter().next().ok_or(Error::NoMatch)
+    }
+}
+
+/// Letters for the puzzle's alphabet, `#priority` for anything wider.
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_char() {
+            Some(c) => write!(f, "{c}"),
+            None => write!(f, "#{}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Item;
+
+    #[test]
+    fn round_trip() {
+        for c in ('a'..='z').chain('A'..='Z') {
+            let item = Item::try_from(c).expect("valid item");
+            assert_eq!(item.to_char(), Some(c));
+            assert_eq!(Item::from_priority(item.priority()), Ok(item));
+        }
+        assert!(Item::try_from('1').is_err());
+        assert!(Item::from_priority(0).is_err());
+        assert_eq!(Item::from_priority(53).unwrap().to_string(), "#53");
+    }
+}
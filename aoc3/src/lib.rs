@@ -0,0 +1,23 @@
+mod alphabet;
+mod bitmap;
+mod bits;
+mod counts;
+mod item;
+pub mod simd;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use alphabet::{Alphabet, Ascii, Letters, Table, Unicode};
+pub use bitmap::{BitmapSet, Iter};
+pub use bits::{Bits, DynamicBits};
+pub use counts::ItemCounts;
+pub use item::Item;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    InvalidItem,
+    InvalidLength,
+    InvalidTable,
+    NoMatch,
+    IncompleteGroup { expected: usize, found: usize },
+}
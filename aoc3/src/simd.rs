@@ -0,0 +1,164 @@
+//! Batch evaluation of rucksacks for the puzzle's [`crate::Letters`] alphabet, building
+//! `u64` masks straight from bytes with AVX2 when the CPU has it.
+
+use crate::{BitmapSet, Error};
+
+/// Mask of the letters in `bytes`, bit `priority - 1` per item.
+pub fn letters_mask(bytes: &[u8]) -> Result<BitmapSet, Error> {
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("avx2") {
+        // Safety: AVX2 support was just checked.
+        return unsafe { avx2::mask(bytes) }.map(BitmapSet::from_bits);
+    }
+    scalar_mask(bytes).map(BitmapSet::from_bits)
+}
+
+fn scalar_mask(bytes: &[u8]) -> Result<u64, Error> {
+    bytes.iter().try_fold(0, |mask, &b| {
+        let bit = match b {
+            b'a'..=b'z' => b - b'a',
+            b'A'..=b'Z' => b - b'A' + 26,
+            _ => return Err(Error::InvalidItem),
+        };
+        Ok(mask | 1 << bit)
+    })
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    use crate::Error;
+
+    /// # Safety
+    /// The CPU must support AVX2.
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn mask(bytes: &[u8]) -> Result<u64, Error> {
+        let mut chunks = bytes.chunks_exact(32);
+        let mut acc = _mm256_setzero_si256();
+        let ones = _mm256_set1_epi64x(1);
+        let mut bits = [0u8; 32];
+        for chunk in &mut chunks {
+            let v = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+            // Bytes >= 0x80 compare as negative so they fail both ranges.
+            let lower = _mm256_and_si256(
+                _mm256_cmpgt_epi8(v, _mm256_set1_epi8(b'a' as i8 - 1)),
+                _mm256_cmpgt_epi8(_mm256_set1_epi8(b'z' as i8 + 1), v),
+            );
+            let upper = _mm256_and_si256(
+                _mm256_cmpgt_epi8(v, _mm256_set1_epi8(b'A' as i8 - 1)),
+                _mm256_cmpgt_epi8(_mm256_set1_epi8(b'Z' as i8 + 1), v),
+            );
+            let valid = _mm256_or_si256(lower, upper);
+            if _mm256_movemask_epi8(valid) != -1 {
+                return Err(Error::InvalidItem);
+            }
+            let index = _mm256_blendv_epi8(
+                _mm256_sub_epi8(v, _mm256_set1_epi8(b'A' as i8 - 26)),
+                _mm256_sub_epi8(v, _mm256_set1_epi8(b'a' as i8)),
+                lower,
+            );
+            _mm256_storeu_si256(bits.as_mut_ptr() as *mut __m256i, index);
+            for quad in bits.chunks_exact(4) {
+                let quad =
+                    _mm_cvtsi32_si128(i32::from_le_bytes([quad[0], quad[1], quad[2], quad[3]]));
+                let shifts = _mm256_cvtepu8_epi64(quad);
+                acc = _mm256_or_si256(acc, _mm256_sllv_epi64(ones, shifts));
+            }
+        }
+        let mut lanes = [0u64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, acc);
+        let tail = super::scalar_mask(chunks.remainder())?;
+        Ok(lanes.iter().fold(tail, |mask, lane| mask | lane))
+    }
+}
+
+fn lines(input: &[u8]) -> impl Iterator<Item = &[u8]> {
+    input
+        .split(|&b| b == b'\n')
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(|l| !l.is_empty())
+}
+
+/// Items shared by both pockets of every rucksack, one rucksack per non-empty line.
+pub fn batch_shared(input: &[u8]) -> Result<Vec<BitmapSet>, Error> {
+    lines(input)
+        .map(|line| {
+            if line.len() & 1 > 0 {
+                return Err(Error::InvalidLength);
+            }
+            let (left, right) = line.split_at(line.len() / 2);
+            Ok(letters_mask(left)? & letters_mask(right)?)
+        })
+        .collect()
+}
+
+/// Items carried by every rucksack in each run of `size` non-empty lines.
+pub fn batch_badges(input: &[u8], size: usize) -> Result<Vec<BitmapSet>, Error> {
+    let masks = lines(input)
+        .map(letters_mask)
+        .collect::<Result<Vec<_>, _>>()?;
+    if size == 0 || masks.len() % size != 0 {
+        return Err(Error::IncompleteGroup {
+            expected: size,
+            found: masks.len() % size.max(1),
+        });
+    }
+    Ok(masks
+        .chunks_exact(size)
+        .map(|group| group.iter().fold(BitmapSet::all(), |l, &r| l & r))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        simd::{batch_badges, batch_shared, letters_mask, scalar_mask},
+        BitmapSet, Error, Item,
+    };
+
+    #[test]
+    fn matches_scalar() {
+        let line = b"vJrwpWtwJgWrhcsFMMfFFhFpjqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSLabcxyzABCXYZ";
+        for len in 0..line.len() {
+            let expected: BitmapSet = line[..len]
+                .iter()
+                .map(|&b| Item::try_from(b as char).unwrap())
+                .collect();
+            assert_eq!(letters_mask(&line[..len]), Ok(expected));
+            assert_eq!(
+                scalar_mask(&line[..len]).map(BitmapSet::from_bits),
+                Ok(expected)
+            );
+        }
+        let mut bad = line.to_vec();
+        bad[40] = b'1';
+        assert_eq!(letters_mask(&bad), Err(Error::InvalidItem));
+        bad[40] = 0xC3;
+        assert_eq!(letters_mask(&bad), Err(Error::InvalidItem));
+    }
+
+    #[test]
+    fn example() {
+        let input = b"vJrwpWtwJgWrhcsFMMfFFhFp\njqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL\nPmmdzqPrVvPwwTWBwg\r\nwMqvLMZHhHMvwLHjbvcjnnSBnvTQFn\nttgJtRGJQctTZtZT\nCrZsJsPPZsGzwwsLwLmpwMDw\n";
+        let shared: Vec<_> = batch_shared(input)
+            .unwrap()
+            .into_iter()
+            .map(|s| Item::try_from(s).unwrap().priority())
+            .collect();
+        assert_eq!(shared, vec![16, 38, 42, 22, 20, 19]);
+        let badges: Vec<_> = batch_badges(input, 3)
+            .unwrap()
+            .into_iter()
+            .map(|s| Item::try_from(s).unwrap().priority())
+            .collect();
+        assert_eq!(badges, vec![18, 52]);
+        assert_eq!(
+            batch_badges(input, 4),
+            Err(Error::IncompleteGroup {
+                expected: 4,
+                found: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Repeatable inputs for the tests and benchmarks, built only with the `testing` feature.
+
+/// A small deterministic generator.
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number below `n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+}
+
+const SEED: u64 = 0x2022_1203;
+
+/// `count` rucksacks of pockets `len` long with exactly one shared item, one per line.
+pub fn rucksacks(count: usize, len: usize) -> String {
+    let mut random = Random::new(SEED);
+    let mut out = String::new();
+    for _ in 0..count {
+        let shared = (b'a'..=b'z')
+            .chain(b'A'..=b'Z')
+            .nth(random.below(52))
+            .unwrap();
+        let mut left: Vec<u8> = (0..len).map(|_| b'a' + random.below(26) as u8).collect();
+        let mut right: Vec<u8> = (0..len).map(|_| b'A' + random.below(26) as u8).collect();
+        left.retain(|&b| b != shared);
+        right.retain(|&b| b != shared);
+        left.truncate(len - 1);
+        right.truncate(len - 1);
+        left.resize(len, shared);
+        right.resize(len, shared);
+        out.push_str(std::str::from_utf8(&left).unwrap());
+        out.push_str(std::str::from_utf8(&right).unwrap());
+        out.push('\n');
+    }
+    out
+}
+
+/// `groups` groups of three rucksacks of pockets `len` long with exactly one common item.
+pub fn groups(groups: usize, len: usize) -> String {
+    let mut random = Random::new(SEED);
+    let letters: Vec<u8> = (b'a'..=b'z').chain(b'A'..=b'Z').collect();
+    let mut out = String::new();
+    for _ in 0..groups {
+        let badge = letters[random.below(52)];
+        let others: Vec<u8> = letters.iter().copied().filter(|&b| b != badge).collect();
+        // Each rucksack skips a different third of the other letters.
+        for skip in 0..3 {
+            let mut line: Vec<u8> = (0..len * 2 - 1)
+                .map(|_| others[random.below(51)])
+                .filter(|&b| others.iter().position(|&o| o == b).unwrap() % 3 != skip)
+                .collect();
+            line.resize(len * 2 - 1, others[(skip + 1) % 3]);
+            line.push(badge);
+            out.push_str(std::str::from_utf8(&line).unwrap());
+            out.push('\n');
+        }
+    }
+    out
+}
@@ -1,45 +1,4 @@
-#[derive(Debug)]
-enum Error {
-    InvalidItem,
-    InvalidLength,
-    NoMatch,
-}
-
-#[derive(Debug, Clone, Copy)]
-struct Item(u8);
-impl Item {
-    fn priority(self) -> u8 {
-        self.0
-    }
-}
-
-impl TryFrom<char> for Item {
-    type Error = Error;
-
-    fn try_from(c: char) -> Result<Self, Self::Error> {
-        match c {
-            'a'..='z' => Ok(Item(c as u8 - 'a' as u8 + 1)),
-            'A'..='Z' => Ok(Item(c as u8 - 'A' as u8 + 27)),
-            _ => Err(Error::InvalidItem),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy)]
-struct BitmapSet(u64);
-impl BitmapSet {
-    fn new() -> Self {
-        BitmapSet(0)
-    }
-
-    fn insert(&mut self, item: Item) {
-        self.0 |= 1 << item.priority()
-    }
-
-    fn contains(self, item: Item) -> bool {
-        (self.0 & 1 << item.priority()) != 0
-    }
-}
+use aoc3::{BitmapSet, Error, Item};
 
 fn overlap(contents: &str) -> Result<Item, Error> {
     let len = contents.len();
@@ -47,12 +6,12 @@ fn overlap(contents: &str) -> Result<Item, Error> {
         return Err(Error::InvalidLength);
     }
     let (left, right) = contents.split_at(len / 2);
-    let mut pocket = BitmapSet::new();
+    let mut pocket: BitmapSet = BitmapSet::new();
     for c in left.chars() {
         pocket.insert(c.try_into()?);
     }
     for c in right.chars() {
-        let item = c.try_into()?;
+        let item: Item = c.try_into()?;
         if pocket.contains(item) {
             return Ok(item);
         }
@@ -61,6 +20,17 @@ fn overlap(contents: &str) -> Result<Item, Error> {
 }
 
 fn main() {
+    if std::env::args().any(|a| a == "--simd") {
+        let mut input = vec![];
+        std::io::Read::read_to_end(&mut std::io::stdin(), &mut input).expect("Reading input");
+        let value: u64 = aoc3::simd::batch_shared(&input)
+            .expect("Valid rucksacks")
+            .into_iter()
+            .map(|s| Item::try_from(s).expect("Shared item").priority() as u64)
+            .sum();
+        println!("Result: {value}");
+        return;
+    }
     let value: u64 = std::io::stdin()
         .lines()
         .map(|l| l.expect("Reading line"))
@@ -73,6 +43,7 @@ fn main() {
 #[cfg(test)]
 mod test {
     use crate::overlap;
+    use aoc3::{simd::batch_shared, testing::rucksacks, Item};
 
     #[test]
     fn example() {
@@ -93,4 +64,31 @@ mod test {
         assert_eq!(overlap("ttgJtRGJQctTZtZT").unwrap().priority(), 20);
         assert_eq!(overlap("CrZsJsPPZsGzwwsLwLmpwMDw").unwrap().priority(), 19);
     }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_overlap() {
+        for len in [12, 24, 48, 200] {
+            let input = rucksacks(100_000, len);
+            let start = std::time::Instant::now();
+            let expected: u32 = input
+                .lines()
+                .map(|l| overlap(std::hint::black_box(l)).unwrap().priority())
+                .sum();
+            let scalar = start.elapsed();
+            let start = std::time::Instant::now();
+            let batched: u32 = batch_shared(std::hint::black_box(input.as_bytes()))
+                .unwrap()
+                .into_iter()
+                .map(|s| Item::try_from(s).unwrap().priority())
+                .sum();
+            let simd = start.elapsed();
+            assert_eq!(expected, batched);
+            println!(
+                "pocket length {len:>3}: overlap {scalar:>10.2?}, batch_shared {simd:>10.2?}, {:.1}x",
+                scalar.as_secs_f64() / simd.as_secs_f64()
+            );
+        }
+    }
 }
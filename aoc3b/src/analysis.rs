@@ -0,0 +1,153 @@
+use std::fmt::Display;
+
+use aoc3::{BitmapSet, Bits, Error, Item};
+
+use crate::Group;
+
+/// Classification of a shared item set against the puzzle's "exactly one" promise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overlap<B: Bits = u64> {
+    None,
+    Single(Item),
+    Multiple(BitmapSet<B>),
+}
+
+impl<B: Bits> From<BitmapSet<B>> for Overlap<B> {
+    fn from(set: BitmapSet<B>) -> Self {
+        match set.len() {
+            0 => Overlap::None,
+            1 => set.try_into().map_or(Overlap::None, Overlap::Single),
+            _ => Overlap::Multiple(set),
+        }
+    }
+}
+
+impl<B: Bits> Display for Overlap<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Overlap::None => write!(f, "none"),
+            Overlap::Single(item) => write!(f, "{item}"),
+            Overlap::Multiple(set) => write!(f, "{set} (multiple)"),
+        }
+    }
+}
+
+/// Running totals for one kind of overlap (rucksack pockets or group badges).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub count: usize,
+    pub none: usize,
+    pub single: usize,
+    pub multiple: usize,
+    /// Sum of the priorities of every shared item, not just the single ones.
+    pub priority: u32,
+}
+
+impl Summary {
+    fn add<B: Bits>(&mut self, shared: &BitmapSet<B>) {
+        self.count += 1;
+        match Overlap::from(shared.clone()) {
+            Overlap::None => self.none += 1,
+            Overlap::Single(_) => self.single += 1,
+            Overlap::Multiple(_) => self.multiple += 1,
+        }
+        self.priority += shared.iter().map(|i| i.priority()).sum::<u32>();
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} total, {} single, {} none, {} multiple, priority sum {}",
+            self.count, self.single, self.none, self.multiple, self.priority
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct GroupReport<B: Bits = u64> {
+    pub rucksacks: Vec<BitmapSet<B>>,
+    pub badges: BitmapSet<B>,
+}
+
+#[derive(Debug)]
+pub struct Analysis<B: Bits = u64> {
+    pub groups: Vec<GroupReport<B>>,
+    pub rucksacks: Summary,
+    pub badges: Summary,
+}
+
+impl<B: Bits> Default for Analysis<B> {
+    fn default() -> Self {
+        Self {
+            groups: vec![],
+            rucksacks: Summary::default(),
+            badges: Summary::default(),
+        }
+    }
+}
+
+impl<B: Bits> Analysis<B> {
+    pub fn add(&mut self, group: &Group<B>) {
+        let rucksacks: Vec<_> = group.0.iter().map(|r| r.shared()).collect();
+        let badges = group.shared();
+        rucksacks.iter().for_each(|s| self.rucksacks.add(s));
+        self.badges.add(&badges);
+        self.groups.push(GroupReport { rucksacks, badges });
+    }
+
+    pub fn analyze(groups: impl Iterator<Item = Result<Group<B>, Error>>) -> Result<Self, Error> {
+        let mut analysis = Analysis::default();
+        for group in groups {
+            analysis.add(&group?);
+        }
+        Ok(analysis)
+    }
+}
+
+impl<B: Bits> Display for Analysis<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut line = 0;
+        for (g, group) in self.groups.iter().enumerate() {
+            writeln!(
+                f,
+                "Group {}: badge {}",
+                g + 1,
+                Overlap::from(group.badges.clone())
+            )?;
+            for shared in &group.rucksacks {
+                line += 1;
+                writeln!(
+                    f,
+                    "  Rucksack {line}: shared {}",
+                    Overlap::from(shared.clone())
+                )?;
+            }
+        }
+        writeln!(f, "Rucksacks: {}", self.rucksacks)?;
+        write!(f, "Groups: {}", self.badges)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{analysis::Analysis, fixed, GroupIter};
+
+    #[test]
+    fn flags_irregular_overlaps() {
+        let data = ["abca", "cBXD", "xyzc", "abab"];
+        let analysis = Analysis::analyze(GroupIter::new(data.iter(), fixed(2))).expect("analysis");
+        assert_eq!(analysis.rucksacks.count, 4);
+        assert_eq!(analysis.rucksacks.single, 1);
+        assert_eq!(analysis.rucksacks.none, 2);
+        assert_eq!(analysis.rucksacks.multiple, 1);
+        assert_eq!(analysis.rucksacks.priority, 1 + (1 + 2));
+        assert_eq!(analysis.badges.single, 1);
+        assert_eq!(analysis.badges.none, 1);
+        assert_eq!(analysis.badges.priority, 3);
+        assert!(analysis
+            .to_string()
+            .contains("Rucksack 4: shared {a, b} (multiple)"));
+    }
+}
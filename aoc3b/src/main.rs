@@ -1,148 +1,463 @@
-use std::str::FromStr;
+use std::{marker::PhantomData, num::NonZeroUsize, str::FromStr};
 
-#[derive(Debug)]
-enum Error {
-    InvalidItem,
-    InvalidLength,
-    NoMatch,
-}
+use aoc3::{
+    Alphabet, Ascii, BitmapSet, Bits, DynamicBits, Error, Item, ItemCounts, Letters, Table, Unicode,
+};
 
-#[derive(Debug, Clone, Copy)]
-struct Item(u8);
-impl Item {
-    fn priority(self) -> u8 {
-        self.0
-    }
-}
+mod analysis;
+mod repack;
 
-impl TryFrom<BitmapSet> for Item {
-    type Error = Error;
+/// The items of one pocket, counted only when a mode needs quantities so the badge search
+/// keeps to the bitmap.
+#[derive(Debug)]
+struct Pocket<B: Bits = u64> {
+    set: BitmapSet<B>,
+    counts: Option<ItemCounts>,
+}
 
-    fn try_from(value: BitmapSet) -> Result<Self, Self::Error> {
-        let mut v = 1;
-        for i in 1..=53 {
-            if v == value.0 {
-                return Ok(Item(i));
+impl<B: Bits> Pocket<B> {
+    fn parse(s: &str, alphabet: &impl Alphabet, counted: bool) -> Result<Self, Error> {
+        let mut set = BitmapSet::new();
+        let mut counts = counted.then(ItemCounts::new);
+        for c in s.chars() {
+            let item = alphabet.item(c)?;
+            set.try_insert(item)?;
+            if let Some(counts) = &mut counts {
+                counts.add(item, 1);
             }
-            v <<= 1;
         }
-        return Err(Error::NoMatch);
+        Ok(Self { set, counts })
     }
-}
-
-impl TryFrom<char> for Item {
-    type Error = Error;
 
-    fn try_from(c: char) -> Result<Self, Self::Error> {
-        match c {
-            'a'..='z' => Ok(Item(c as u8 - 'a' as u8 + 1)),
-            'A'..='Z' => Ok(Item(c as u8 - 'A' as u8 + 27)),
-            _ => Err(Error::InvalidItem),
-        }
+    /// The items by quantity, if the pocket was parsed counted.
+    fn counts(&self) -> Option<&ItemCounts> {
+        self.counts.as_ref()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-struct BitmapSet(u64);
-impl BitmapSet {
-    fn new() -> Self {
-        BitmapSet(0)
+#[derive(Debug)]
+struct Rucksack<B: Bits = u64>(Pocket<B>, Pocket<B>);
+
+impl<B: Bits> Rucksack<B> {
+    fn parse(s: &str, alphabet: &impl Alphabet, counted: bool) -> Result<Self, Error> {
+        let len = s.chars().count();
+        if len & 1 > 0 {
+            return Err(Error::InvalidLength);
+        }
+        let middle = s.char_indices().nth(len / 2).map_or(s.len(), |(i, _)| i);
+        let (left, right) = s.split_at(middle);
+        Ok(Rucksack(
+            Pocket::parse(left, alphabet, counted)?,
+            Pocket::parse(right, alphabet, counted)?,
+        ))
     }
 
-    fn insert(&mut self, item: Item) {
-        self.0 |= 1 << (item.priority() - 1)
+    fn full_set(&self) -> BitmapSet<B> {
+        self.0.set.union(&self.1.set)
     }
 
-    fn union(self, rhs: Self) -> Self {
-        Self(self.0 | rhs.0)
+    /// Every item found in both pockets.
+    fn shared(&self) -> BitmapSet<B> {
+        self.0.set.intersect(&self.1.set)
     }
 
-    fn intersect(self, rhs: Self) -> Self {
-        Self(self.0 & rhs.0)
+    /// Copies carried across both pockets of each shared item, if the rucksack was counted.
+    fn duplicated(&self) -> Option<ItemCounts> {
+        let (left, right) = (self.0.counts()?, self.1.counts()?);
+        let shared = self.shared();
+        if shared.is_empty() {
+            return Some(ItemCounts::new());
+        }
+        Some(left.sum(right).restrict(&shared))
     }
 }
 
-#[derive(Debug)]
-struct Pocket(BitmapSet);
-
-impl FromStr for Pocket {
+impl FromStr for Rucksack {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut set = BitmapSet::new();
-        for c in s.chars() {
-            set.insert(c.try_into()?);
-        }
-        Ok(Self(set))
+        Self::parse(s, &Letters, true)
     }
 }
 
 #[derive(Debug)]
-struct Rucksack(Pocket, Pocket);
+struct Group<B: Bits = u64>(Vec<Rucksack<B>>);
 
-impl FromStr for Rucksack {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let len = s.len();
-        if len & 1 > 0 {
+impl<B: Bits> Group<B> {
+    fn badge(&self) -> Result<Item, Error> {
+        if self.0.is_empty() {
             return Err(Error::InvalidLength);
         }
-        let (left, right) = s.split_at(len / 2);
-        Ok(Rucksack(left.parse()?, right.parse()?))
+        self.shared().try_into()
+    }
+
+    /// Copies of each item the rucksacks carry in both of their pockets, if they were counted.
+    fn duplicated(&self) -> Option<ItemCounts> {
+        self.0
+            .iter()
+            .try_fold(ItemCounts::new(), |acc, r| Some(acc.sum(&r.duplicated()?)))
+    }
+
+    /// Every item carried by all rucksacks in the group.
+    fn shared(&self) -> BitmapSet<B> {
+        self.0
+            .iter()
+            .map(|v| v.full_set())
+            .reduce(|l, r| l.intersect(&r))
+            .unwrap_or_default()
     }
 }
 
-impl Rucksack {
-    fn full_set(&self) -> BitmapSet {
-        self.0 .0.union(self.1 .0)
+/// How consecutive input lines are split into groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Grouping {
+    /// Every `n` lines form a group, a short final group is an error.
+    Fixed(NonZeroUsize),
+    /// Groups are separated by one or more blank lines.
+    BlankLine,
+    /// Each line starting with the prefix begins a new group and is not itself a rucksack.
+    Header(String),
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Grouping::Fixed(NonZeroUsize::new(3).unwrap())
     }
 }
 
-#[derive(Debug)]
-struct Group([Rucksack; 3]);
+struct GroupIter<I: Iterator<Item = T>, T: AsRef<str>, A: Alphabet = Letters, B: Bits = u64> {
+    lines: std::iter::Peekable<I>,
+    grouping: Grouping,
+    alphabet: A,
+    counted: bool,
+    bits: PhantomData<B>,
+}
 
-impl Group {
-    fn badge(&self) -> Result<Item, Error> {
-        self.0
+impl<I: Iterator<Item = T>, T: AsRef<str>> GroupIter<I, T> {
+    fn new(inner: I, grouping: Grouping) -> Self {
+        Self::with_alphabet(inner, grouping, Letters)
+    }
+}
+
+impl<I: Iterator<Item = T>, T: AsRef<str>, A: Alphabet, B: Bits> GroupIter<I, T, A, B> {
+    fn with_alphabet(inner: I, grouping: Grouping, alphabet: A) -> Self {
+        Self {
+            lines: inner.peekable(),
+            grouping,
+            alphabet,
+            counted: false,
+            bits: PhantomData,
+        }
+    }
+
+    /// Counts every item as well, for the modes that need quantities.
+    fn counted(mut self) -> Self {
+        self.counted = true;
+        self
+    }
+
+    fn parse(&self, lines: &[T]) -> Result<Vec<Rucksack<B>>, Error> {
+        lines
             .iter()
-            .map(|v| v.full_set())
-            .reduce(|l, r| l.intersect(r))
-            .ok_or(Error::InvalidLength)?
-            .to_owned()
-            .try_into()
+            .map(|l| Rucksack::parse(l.as_ref(), &self.alphabet, self.counted))
+            .collect()
+    }
+
+    fn next_fixed(&mut self, size: usize) -> Option<Result<Vec<Rucksack<B>>, Error>> {
+        let mut lines = vec![];
+        while lines.len() < size {
+            let Some(line) = self.lines.next() else {
+                break;
+            };
+            lines.push(line);
+        }
+        match lines.len() {
+            0 => None,
+            found if found < size => Some(Err(Error::IncompleteGroup {
+                expected: size,
+                found,
+            })),
+            _ => Some(self.parse(&lines)),
+        }
+    }
+
+    fn next_until(
+        &mut self,
+        is_end: impl Fn(&str) -> bool,
+    ) -> Option<Result<Vec<Rucksack<B>>, Error>> {
+        let mut lines = vec![];
+        while let Some(line) = self.lines.next_if(|l| !is_end(l.as_ref())) {
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        Some(self.parse(&lines))
+    }
+
+    fn is_blank(line: &str) -> bool {
+        line.trim().is_empty()
     }
 }
 
-struct GroupIter<I: Iterator<Item = T>, T: AsRef<str>>(I);
-impl<I: Iterator<Item = T>, T: AsRef<str>> Iterator for GroupIter<I, T> {
-    type Item = Group;
+impl<I: Iterator<Item = T>, T: AsRef<str>, A: Alphabet, B: Bits> Iterator
+    for GroupIter<I, T, A, B>
+{
+    type Item = Result<Group<B>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        Some(Group([
-            self.0.next()?.as_ref().parse().ok()?,
-            self.0.next()?.as_ref().parse().ok()?,
-            self.0.next()?.as_ref().parse().ok()?,
-        ]))
+        let rucksacks = match self.grouping.clone() {
+            Grouping::Fixed(size) => self.next_fixed(size.get()),
+            Grouping::BlankLine => {
+                while self.lines.next_if(|l| Self::is_blank(l.as_ref())).is_some() {}
+                self.next_until(Self::is_blank)
+            }
+            Grouping::Header(prefix) => {
+                while self.lines.next_if(|l| Self::is_blank(l.as_ref())).is_some() {}
+                let header = self.lines.next_if(|l| l.as_ref().starts_with(&prefix));
+                let group = self.next_until(|l| Self::is_blank(l) || l.starts_with(&prefix));
+                match (header, group) {
+                    (Some(_), None) => Some(Ok(vec![])),
+                    (_, group) => group,
+                }
+            }
+        };
+        Some(rucksacks?.map(Group))
     }
 }
+
 impl<I: Iterator<Item = T>, T: AsRef<str>> From<I> for GroupIter<I, T> {
     fn from(inner: I) -> Self {
-        Self(inner)
+        Self::new(inner, Grouping::default())
     }
 }
 
-fn main() {
-    let iter: GroupIter<_, _> = std::io::stdin().lines().map_while(|v| v.ok()).into();
+#[derive(Debug, Default)]
+enum AlphabetChoice {
+    #[default]
+    Letters,
+    Ascii,
+    Unicode,
+    Table(Table),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    #[default]
+    Badges,
+    Analyze,
+    Repack,
+    Counts,
+}
+
+#[derive(Debug)]
+struct Options {
+    grouping: Grouping,
+    mode: Mode,
+    simd: bool,
+    alphabet: AlphabetChoice,
+    /// Most partial packings the repacking search keeps at once.
+    limit: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            grouping: Grouping::default(),
+            mode: Mode::default(),
+            simd: false,
+            alphabet: AlphabetChoice::default(),
+            limit: 1_000_000,
+        }
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Options {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--size" => {
+                let size = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .expect("--size needs a positive number");
+                options.grouping = Grouping::Fixed(size);
+            }
+            "--blank" => options.grouping = Grouping::BlankLine,
+            "--header" => {
+                options.grouping = Grouping::Header(args.next().expect("--header needs a prefix"))
+            }
+            "--analyze" => options.mode = Mode::Analyze,
+            "--repack" => options.mode = Mode::Repack,
+            "--counts" => options.mode = Mode::Counts,
+            "--simd" => options.simd = true,
+            "--limit" => {
+                options.limit = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .expect("--limit needs a number")
+            }
+            "--alphabet" => {
+                options.alphabet = match args.next().as_deref() {
+                    Some("letters") => AlphabetChoice::Letters,
+                    Some("ascii") => AlphabetChoice::Ascii,
+                    Some("unicode") => AlphabetChoice::Unicode,
+                    _ => panic!("--alphabet needs one of letters, ascii or unicode"),
+                }
+            }
+            "--table" => {
+                let path = args.next().expect("--table needs a file");
+                let table = std::fs::read_to_string(path).expect("Couldn't read table");
+                options.alphabet = AlphabetChoice::Table(table.parse().expect("Invalid table"));
+            }
+            arg => panic!(
+                "Unknown argument {arg}, expected --size N, --blank, --header PREFIX, --analyze, --repack, --limit N, --counts, --simd, --alphabet NAME or --table FILE"
+            ),
+        }
+    }
+    options
+}
+
+fn describe_duplicates(counts: &ItemCounts, alphabet: &impl Alphabet) -> String {
+    let Some((item, count)) = counts.most_common() else {
+        return "nothing in both pockets".to_string();
+    };
+    let symbol = alphabet
+        .symbol(item)
+        .map_or_else(|| item.to_string(), String::from);
+    format!(
+        "most duplicated {symbol} ({count} copies), weighted priority {}",
+        counts.weighted_priority()
+    )
+}
+
+fn run<A: Alphabet, B: Bits>(grouping: Grouping, mode: Mode, limit: usize, alphabet: A) {
+    let mut iter: GroupIter<_, _, A, B> = GroupIter::with_alphabet(
+        std::io::stdin().lines().map_while(|v| v.ok()),
+        grouping,
+        alphabet,
+    );
+    if matches!(mode, Mode::Counts | Mode::Repack) {
+        iter = iter.counted();
+    }
+    match mode {
+        Mode::Badges => {}
+        Mode::Analyze => {
+            let analysis = analysis::Analysis::analyze(iter).expect("Invalid group");
+            println!("{analysis}");
+            return;
+        }
+        Mode::Counts => {
+            let mut total = 0;
+            let mut line = 0;
+            let mut number = 0;
+            while let Some(group) = iter.next() {
+                let group = group.expect("Invalid group");
+                number += 1;
+                println!(
+                    "Group {number}: {}",
+                    describe_duplicates(
+                        &group.duplicated().expect("Groups are read counted"),
+                        &iter.alphabet
+                    )
+                );
+                for rucksack in &group.0 {
+                    line += 1;
+                    let duplicated = rucksack.duplicated().expect("Groups are read counted");
+                    total += duplicated.weighted_priority();
+                    println!(
+                        "  Rucksack {line}: {}",
+                        describe_duplicates(&duplicated, &iter.alphabet)
+                    );
+                }
+            }
+            println!("Weighted priority: {total}");
+            return;
+        }
+        Mode::Repack => {
+            let mut number = 0;
+            while let Some(group) = iter.next() {
+                number += 1;
+                println!("Group {number}:");
+                let group = group.expect("Invalid group");
+                let packing = repack::packing(&group).expect("Groups are read counted");
+                let plan = match repack::plan(&packing, limit) {
+                    Ok(plan) => plan,
+                    Err(repack::NoPlan::Impossible) => {
+                        println!("  No packing keeps both pockets equal");
+                        continue;
+                    }
+                    Err(repack::NoPlan::SearchLimit) => {
+                        println!("  Gave up after {limit} partial packings, try a higher --limit");
+                        continue;
+                    }
+                };
+                for m in &plan.moves {
+                    println!("  {}", m.describe(&iter.alphabet));
+                }
+                for line in plan.render(&iter.alphabet) {
+                    println!("  {line}");
+                }
+            }
+            return;
+        }
+    }
     let value: u32 = iter
-        .map(|g| g.badge().expect("Couldn't get badge").priority() as u32)
+        .map(|g| g.expect("Invalid group"))
+        .map(|g| g.badge().expect("Couldn't get badge").priority())
+        .sum();
+    println!("Result: {value}");
+}
+
+/// Letters only, fixed size groups only.
+fn run_simd(grouping: Grouping) {
+    let Grouping::Fixed(size) = grouping else {
+        panic!("--simd only supports --size");
+    };
+    let mut input = vec![];
+    std::io::Read::read_to_end(&mut std::io::stdin(), &mut input).expect("Reading input");
+    let value: u32 = aoc3::simd::batch_badges(&input, size.get())
+        .expect("Invalid group")
+        .into_iter()
+        .map(|s| Item::try_from(s).expect("Couldn't get badge").priority())
         .sum();
     println!("Result: {value}");
 }
 
+fn main() {
+    let Options {
+        grouping,
+        mode,
+        simd,
+        alphabet,
+        limit,
+    } = parse_options(std::env::args().skip(1));
+    match alphabet {
+        AlphabetChoice::Letters if simd && mode == Mode::Badges => run_simd(grouping),
+        AlphabetChoice::Letters => run::<_, u64>(grouping, mode, limit, Letters),
+        AlphabetChoice::Ascii => run::<_, u128>(grouping, mode, limit, Ascii),
+        AlphabetChoice::Unicode => run::<_, DynamicBits>(grouping, mode, limit, Unicode),
+        AlphabetChoice::Table(table) => match table.max_priority() {
+            Some(0..=64) => run::<_, u64>(grouping, mode, limit, table),
+            Some(65..=128) => run::<_, u128>(grouping, mode, limit, table),
+            Some(129..=256) => run::<_, [u64; 4]>(grouping, mode, limit, table),
+            _ => run::<_, DynamicBits>(grouping, mode, limit, table),
+        },
+    }
+}
+
+/// Groups of `size` lines, for tests.
+#[cfg(test)]
+fn fixed(size: usize) -> Grouping {
+    Grouping::Fixed(NonZeroUsize::new(size).unwrap())
+}
+
 #[cfg(test)]
 mod test {
-    use crate::GroupIter;
+    use crate::{fixed, Group, GroupIter, Grouping, Rucksack};
+    use aoc3::{
+        simd::batch_badges, testing::groups, Ascii, DynamicBits, Error, Item, Table, Unicode,
+    };
 
     #[test]
     fn example() {
@@ -158,6 +473,7 @@ mod test {
         assert_eq!(
             v.next()
                 .expect("a group")
+                .expect("valid group")
                 .badge()
                 .expect("badge")
                 .priority(),
@@ -166,10 +482,103 @@ mod test {
         assert_eq!(
             v.next()
                 .expect("a group")
+                .expect("valid group")
                 .badge()
                 .expect("badge")
                 .priority(),
             52
         );
     }
+
+    fn badges<T: AsRef<str>>(data: &[T], grouping: Grouping) -> Result<Vec<u32>, Error> {
+        GroupIter::new(data.iter(), grouping)
+            .map(|g| Ok(g?.badge()?.priority()))
+            .collect()
+    }
+
+    #[test]
+    fn groupings() {
+        let data = ["abcd", "cBXD", "xyzc", "zzzz"];
+        assert_eq!(badges(&data, fixed(2)), Ok(vec![3, 26]));
+        assert_eq!(
+            badges(&data, fixed(3)),
+            Err(Error::IncompleteGroup {
+                expected: 3,
+                found: 1
+            })
+        );
+
+        let data = ["", "abcd", "cBXD", "", "", "xyzc", "zzzz", ""];
+        assert_eq!(badges(&data, Grouping::BlankLine), Ok(vec![3, 26]));
+
+        let data = ["# one", "abcd", "cBXD", "xyAc", "# two", "zzzz"];
+        assert_eq!(
+            badges(&data, Grouping::Header("#".to_string())),
+            Ok(vec![3, 26])
+        );
+    }
+
+    #[test]
+    fn wider_alphabets() {
+        let data = ["a1b!", "!9c7"];
+        let mut ascii: GroupIter<_, _, _, u128> =
+            GroupIter::with_alphabet(data.iter(), fixed(2), Ascii);
+        let badge = ascii.next().unwrap().unwrap().badge().unwrap();
+        assert_eq!(badge.priority(), 63);
+
+        let data = ["ä€ßä", "€xyz"];
+        let mut unicode: GroupIter<_, _, _, DynamicBits> =
+            GroupIter::with_alphabet(data.iter(), fixed(2), Unicode);
+        let badge = unicode.next().unwrap().unwrap().badge().unwrap();
+        assert_eq!(badge.priority(), '€' as u32 + 1);
+
+        let table = Table::ordered("xyz€").unwrap();
+        let mut custom: GroupIter<_, _, _, u64> =
+            GroupIter::with_alphabet(data.iter(), fixed(2), table);
+        assert_eq!(custom.next().unwrap().err(), Some(Error::InvalidItem));
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_badge() {
+        for len in [12, 24, 48, 200] {
+            let input = groups(30_000, len);
+            let start = std::time::Instant::now();
+            let expected: u32 = GroupIter::from(std::hint::black_box(input.as_str()).lines())
+                .map(|g| g.unwrap().badge().unwrap().priority())
+                .sum();
+            let scalar = start.elapsed();
+            let start = std::time::Instant::now();
+            let batched: u32 = batch_badges(std::hint::black_box(input.as_bytes()), 3)
+                .unwrap()
+                .into_iter()
+                .map(|s| Item::try_from(s).unwrap().priority())
+                .sum();
+            let simd = start.elapsed();
+            assert_eq!(expected, batched);
+            println!(
+                "pocket length {len:>3}: badge {scalar:>10.2?}, batch_badges {simd:>10.2?}, {:.1}x",
+                scalar.as_secs_f64() / simd.as_secs_f64()
+            );
+        }
+    }
+
+    #[test]
+    fn duplicates() {
+        let rucksack: Rucksack = "vJrwpWtwJgWrhcsFMMfFFhFp".parse().unwrap();
+        let duplicated = rucksack.duplicated().unwrap();
+        assert_eq!(duplicated.total(), 2);
+        assert_eq!(duplicated.weighted_priority(), 32);
+        let group = Group(vec!["aabbab".parse().unwrap(), "ZZaZaZ".parse().unwrap()]);
+        assert_eq!(
+            group.duplicated().unwrap().most_common(),
+            Some((Item::try_from('a').unwrap(), 5))
+        );
+        let data = ["aabbab", "ZZaZaZ"];
+        let plain = GroupIter::new(data.iter(), fixed(2)).next();
+        assert_eq!(plain.unwrap().unwrap().duplicated(), None);
+        let counted = GroupIter::new(data.iter(), fixed(2)).counted().next();
+        assert_eq!(counted.unwrap().unwrap().duplicated(), group.duplicated());
+    }
 }
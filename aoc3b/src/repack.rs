@@ -0,0 +1,609 @@
+use std::{cmp::Reverse, collections::HashMap, fmt::Display};
+
+use aoc3::{Alphabet, Bits, Item, ItemCounts};
+
+use crate::Group;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    fn index(self) -> usize {
+        match self {
+            Side::Left => 0,
+            Side::Right => 1,
+        }
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Side::Left => write!(f, "left"),
+            Side::Right => write!(f, "right"),
+        }
+    }
+}
+
+/// A pocket, addressed by the rucksack's index within its group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compartment {
+    pub rucksack: usize,
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub item: Item,
+    pub count: u32,
+    pub from: Compartment,
+    pub to: Compartment,
+}
+
+impl Move {
+    pub fn describe(&self, alphabet: &impl Alphabet) -> String {
+        format!(
+            "move {} {} from rucksack {} {} to rucksack {} {}",
+            self.count,
+            symbol(alphabet, self.item),
+            self.from.rucksack + 1,
+            self.from.side,
+            self.to.rucksack + 1,
+            self.to.side,
+        )
+    }
+}
+
+fn symbol(alphabet: &impl Alphabet, item: Item) -> String {
+    match alphabet.symbol(item) {
+        Some(c) => c.to_string(),
+        None => item.to_string(),
+    }
+}
+
+type Pockets = [ItemCounts; 2];
+
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub moves: Vec<Move>,
+    /// Left and right pocket contents of every rucksack after the moves.
+    pub packing: Vec<Pockets>,
+}
+
+impl Plan {
+    /// Number of individual items moved.
+    pub fn cost(&self) -> u32 {
+        self.moves.iter().map(|m| m.count).sum()
+    }
+
+    /// Each rucksack as an input line, items in priority order within each pocket.
+    pub fn render(&self, alphabet: &impl Alphabet) -> Vec<String> {
+        self.packing
+            .iter()
+            .map(|pockets| {
+                pockets
+                    .iter()
+                    .flat_map(|p| p.iter())
+                    .map(|(item, count)| symbol(alphabet, item).repeat(count as usize))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Both pockets of every rucksack by quantity, if the group was read counted.
+pub fn packing<B: Bits>(group: &Group<B>) -> Option<Vec<Pockets>> {
+    group
+        .0
+        .iter()
+        .map(|r| Some([r.0.counts()?.clone(), r.1.counts()?.clone()]))
+        .collect()
+}
+
+/// Cheapest way to give every item type a single pocket while keeping both pockets the same
+/// size. Each type is sent wholly left or right, so this is a subset sum over the types with
+/// the items not already on the chosen side as cost.
+fn balance(rucksack: usize, pockets: &Pockets) -> Option<(Vec<Move>, Pockets)> {
+    let mut types: Vec<(Item, u32, u32)> = pockets[0]
+        .iter()
+        .map(|(item, left)| (item, left, pockets[1].get(item)))
+        .collect();
+    types.extend(
+        pockets[1]
+            .iter()
+            .filter(|&(item, _)| pockets[0].get(item) == 0)
+            .map(|(item, right)| (item, 0, right)),
+    );
+    let total = (pockets[0].total() + pockets[1].total()) as usize;
+    if total & 1 > 0 {
+        return None;
+    }
+
+    // cost[k][s]: fewest moves placing the first k types with s items on the left.
+    let mut cost = vec![vec![u32::MAX; total + 1]; types.len() + 1];
+    cost[0][0] = 0;
+    for (k, &(_, left, right)) in types.iter().enumerate() {
+        let size = (left + right) as usize;
+        for s in 0..=total {
+            let Some(current) = Some(cost[k][s]).filter(|&c| c != u32::MAX) else {
+                continue;
+            };
+            cost[k + 1][s] = cost[k + 1][s].min(current + left);
+            if s + size <= total {
+                cost[k + 1][s + size] = cost[k + 1][s + size].min(current + right);
+            }
+        }
+    }
+    if cost[types.len()][total / 2] == u32::MAX {
+        return None;
+    }
+
+    let mut moves = vec![];
+    let mut packed = [ItemCounts::new(), ItemCounts::new()];
+    let mut s = total / 2;
+    for (k, &(item, left, right)) in types.iter().enumerate().rev() {
+        let size = (left + right) as usize;
+        let to_left = s >= size
+            && cost[k][s - size] != u32::MAX
+            && cost[k][s - size] + right == cost[k + 1][s];
+        let (to, count) = match to_left {
+            true => {
+                s -= size;
+                (Side::Left, right)
+            }
+            false => (Side::Right, left),
+        };
+        packed[to.index()].add(item, left + right);
+        if count > 0 {
+            let from = match to {
+                Side::Left => Side::Right,
+                Side::Right => Side::Left,
+            };
+            moves.push(Move {
+                item,
+                count,
+                from: Compartment {
+                    rucksack,
+                    side: from,
+                },
+                to: Compartment { rucksack, side: to },
+            });
+        }
+    }
+    moves.reverse();
+    Some((moves, packed))
+}
+
+fn balance_all(packing: &[Pockets]) -> Option<Plan> {
+    let mut plan = Plan {
+        moves: vec![],
+        packing: vec![],
+    };
+    for (i, pockets) in packing.iter().enumerate() {
+        let (moves, packed) = balance(i, pockets)?;
+        plan.moves.extend(moves);
+        plan.packing.push(packed);
+    }
+    Some(plan)
+}
+
+/// Why a group has no plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoPlan {
+    /// No packing keeps every pocket its size with each item type in one pocket per rucksack.
+    Impossible,
+    /// More than the limit of partial packings had to be kept at once.
+    SearchLimit,
+}
+
+/// A partial packing: how full each pocket is after placing the first few item types.
+struct Entry {
+    fill: Vec<u32>,
+    cost: u32,
+    parent: usize,
+    /// How many of the latest type went in each pocket.
+    placed: Vec<u32>,
+}
+
+/// The item types still to be placed.
+#[derive(Debug, Clone)]
+struct Rest {
+    /// How many of them each pocket holds now.
+    held: Vec<u32>,
+    /// How many of them each rucksack has to move out, being held in both its pockets.
+    forced: Vec<u32>,
+}
+
+impl Rest {
+    /// The fewest moves still needed to fill the pockets from `fill` to `room`. A pocket
+    /// holding fewer of the remaining items than it has space for needs the rest to arrive;
+    /// one holding more, or a rucksack with a type in both pockets, has items to send away.
+    fn bound(&self, fill: &[u32], room: &[u32]) -> u32 {
+        let space = |p: usize| room[p] - fill[p];
+        let arrive: u32 = (0..room.len())
+            .map(|p| space(p).saturating_sub(self.held[p]))
+            .sum();
+        let depart: u32 = self
+            .forced
+            .iter()
+            .enumerate()
+            .map(|(r, &forced)| {
+                let over: u32 = [r * 2, r * 2 + 1]
+                    .iter()
+                    .map(|&p| self.held[p].saturating_sub(space(p)))
+                    .sum();
+                forced.max(over)
+            })
+            .sum();
+        arrive.max(depart)
+    }
+}
+
+/// Every way to put one type's items, `held` in each pocket now, back in the pockets with no
+/// more than `budget` of them changing pocket. At most one pocket of each rucksack gets any,
+/// and no pocket goes over its room. Each is passed to `visit` with how many items arrived.
+fn placements(
+    held: &[u32],
+    fill: &[u32],
+    room: &[u32],
+    budget: u32,
+    visit: &mut dyn FnMut(&[u32], u32),
+) {
+    struct Search<'a> {
+        held: &'a [u32],
+        fill: &'a [u32],
+        room: &'a [u32],
+        budget: u32,
+        placed: Vec<u32>,
+        visit: &'a mut dyn FnMut(&[u32], u32),
+    }
+    impl Search<'_> {
+        /// Places `left` items in the rucksacks from `rucksack` on, given how many have
+        /// arrived in and departed from pockets so far. Each item moved does both, so the
+        /// larger of the two can't exceed the budget.
+        fn place(&mut self, rucksack: usize, left: u32, arrived: u32, departed: u32) {
+            if arrived.max(departed) > self.budget {
+                return;
+            }
+            if rucksack * 2 == self.room.len() {
+                if left == 0 {
+                    (self.visit)(&self.placed, arrived);
+                }
+                return;
+            }
+            let pockets = [rucksack * 2, rucksack * 2 + 1];
+            let here: u32 = pockets.iter().map(|&p| self.held[p]).sum();
+            self.place(rucksack + 1, left, arrived, departed + here);
+            for pocket in pockets {
+                let other = self.held[pocket ^ 1];
+                for n in 1..=left.min(self.room[pocket] - self.fill[pocket]) {
+                    self.placed[pocket] = n;
+                    let arrived = arrived + n.saturating_sub(self.held[pocket]);
+                    let departed = departed + self.held[pocket].saturating_sub(n) + other;
+                    self.place(rucksack + 1, left - n, arrived, departed);
+                }
+                self.placed[pocket] = 0;
+            }
+        }
+    }
+    let mut search = Search {
+        held,
+        fill,
+        room,
+        budget,
+        placed: vec![0; room.len()],
+        visit,
+    };
+    search.place(0, held.iter().sum(), 0, 0);
+}
+
+/// The moves taking one type from `held` to `placed`, pocket by pocket, preferring to keep
+/// items within their rucksack.
+fn moves(item: Item, held: &[u32], placed: &[u32]) -> Vec<Move> {
+    let compartment = |pocket: usize| Compartment {
+        rucksack: pocket / 2,
+        side: match pocket % 2 {
+            0 => Side::Left,
+            _ => Side::Right,
+        },
+    };
+    let mut surplus: Vec<u32> = held
+        .iter()
+        .zip(placed)
+        .map(|(h, p)| h.saturating_sub(*p))
+        .collect();
+    let mut moves = vec![];
+    for to in 0..placed.len() {
+        let mut need = placed[to].saturating_sub(held[to]);
+        let near = [to ^ 1];
+        let far = (0..held.len()).filter(|&from| from / 2 != to / 2);
+        for from in near.into_iter().chain(far) {
+            let count = need.min(surplus[from]);
+            if count == 0 {
+                continue;
+            }
+            surplus[from] -= count;
+            need -= count;
+            moves.push(Move {
+                item,
+                count,
+                from: compartment(from),
+                to: compartment(to),
+            });
+        }
+    }
+    moves
+}
+
+/// Places the types one at a time, keeping for each way of filling the pockets the cheapest
+/// partial packing that could still end up costing at most `best`. The last layer holds every
+/// complete packing found.
+fn search(
+    types: &[(Item, Vec<u32>)],
+    after: &[Rest],
+    room: &[u32],
+    best: u32,
+    limit: usize,
+) -> Result<Vec<Vec<Entry>>, NoPlan> {
+    let start = Entry {
+        fill: vec![0; room.len()],
+        cost: 0,
+        parent: 0,
+        placed: vec![],
+    };
+    let mut layers: Vec<Vec<Entry>> = vec![vec![start]];
+    for ((_, held), rest) in types.iter().zip(&after[1..]) {
+        let forced: u32 = rest.forced.iter().sum();
+        let mut next: Vec<Entry> = vec![];
+        let mut index: HashMap<Vec<u32>, usize> = HashMap::new();
+        let mut fill = vec![];
+        for (parent, entry) in layers.last().unwrap().iter().enumerate() {
+            let budget = best.saturating_sub(entry.cost + forced);
+            let mut visit = |placed: &[u32], arrived: u32| {
+                let cost = entry.cost + arrived;
+                fill.clear();
+                fill.extend(entry.fill.iter().zip(placed).map(|(f, p)| f + p));
+                if cost + rest.bound(&fill, room) > best {
+                    return;
+                }
+                let entry = Entry {
+                    fill: fill.clone(),
+                    cost,
+                    parent,
+                    placed: placed.to_vec(),
+                };
+                match index.get(&entry.fill) {
+                    Some(&i) if next[i].cost <= cost => {}
+                    Some(&i) => next[i] = entry,
+                    None => {
+                        index.insert(entry.fill.clone(), next.len());
+                        next.push(entry);
+                    }
+                }
+            };
+            placements(held, &entry.fill, room, budget, &mut visit);
+            if next.len() > limit {
+                return Err(NoPlan::SearchLimit);
+            }
+        }
+        layers.push(next);
+    }
+
+    Ok(layers)
+}
+
+/// The fewest item moves, between the pockets of a rucksack or across rucksacks of the group,
+/// so that no item type is in both pockets of any rucksack while every pocket keeps its size.
+///
+/// Item types are placed one at a time, keeping for each way of filling the pockets only the
+/// cheapest way there, so the search is exact. It is run with ever higher costs allowed,
+/// starting from a lower bound on the moves, so the first plan found is the cheapest; when
+/// nothing beats balancing every rucksack on its own, that plan is returned instead. Gives up
+/// with [`NoPlan::SearchLimit`] once more than `limit` partial packings are kept at once.
+pub fn plan(packing: &[Pockets], limit: usize) -> Result<Plan, NoPlan> {
+    let room: Vec<u32> = packing.iter().flatten().map(|p| p.total()).collect();
+    let all = packing
+        .iter()
+        .flatten()
+        .fold(ItemCounts::new(), |a, p| a.sum(p));
+    let mut types: Vec<(Item, Vec<u32>)> = all
+        .iter()
+        .map(|(item, _)| {
+            (
+                item,
+                packing.iter().flatten().map(|p| p.get(item)).collect(),
+            )
+        })
+        .collect();
+    // Types in both pockets of a rucksack first, as their choice of pocket matters most.
+    types.sort_by_key(|(_, held)| Reverse(held.chunks(2).map(|h| h[0].min(h[1])).sum::<u32>()));
+    // What the types from each one on hold in every pocket, and must move out of every
+    // rucksack whichever pocket they keep there.
+    let mut after = vec![Rest {
+        held: vec![0; room.len()],
+        forced: vec![0; packing.len()],
+    }];
+    for (_, held) in types.iter().rev() {
+        let mut rest = after.last().unwrap().clone();
+        for (p, h) in held.iter().enumerate() {
+            rest.held[p] += h;
+        }
+        for (r, h) in held.chunks(2).enumerate() {
+            rest.forced[r] += h[0].min(h[1]);
+        }
+        after.push(rest);
+    }
+    after.reverse();
+    // Try ever dearer plans, from the fewest moves the bound allows up to just below what
+    // balancing every rucksack alone costs, or up to moving every item once.
+    let local = balance_all(packing);
+    let most: u32 = room.iter().sum();
+    let start = vec![0; room.len()];
+    let mut layers = vec![];
+    for best in after[0].bound(&start, &room)..local.as_ref().map_or(most + 1, Plan::cost) {
+        layers = search(&types, &after, &room, best, limit)?;
+        if !layers.last().unwrap().is_empty() {
+            break;
+        }
+    }
+    let Some(last) = layers.last().filter(|l| !l.is_empty()) else {
+        return local.ok_or(NoPlan::Impossible);
+    };
+    let mut at = (0..last.len()).min_by_key(|&i| last[i].cost).unwrap();
+    let mut plan = Plan {
+        moves: vec![],
+        packing: vec![[ItemCounts::new(), ItemCounts::new()]; packing.len()],
+    };
+    for ((item, held), layer) in types.iter().zip(&layers[1..]).rev() {
+        let entry = &layer[at];
+        for (pocket, &count) in entry.placed.iter().enumerate() {
+            plan.packing[pocket / 2][pocket % 2].add(*item, count);
+        }
+        plan.moves.splice(0..0, moves(*item, held, &entry.placed));
+        at = entry.parent;
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod test {
+    use aoc3::{testing::Random, Item, ItemCounts, Letters};
+
+    use super::{NoPlan, Plan, Side};
+
+    use crate::{
+        repack::{packing, plan},
+        Group, Rucksack,
+    };
+
+    fn plan_rucksack(line: &str) -> Result<Plan, NoPlan> {
+        plan(
+            &packing(&Group(vec![line.parse().unwrap()])).unwrap(),
+            1_000,
+        )
+    }
+
+    #[test]
+    fn rucksack() {
+        let plan = plan_rucksack("vJrwpWtwJgWrhcsFMMfFFhFp").expect("a plan");
+        // Unifying `p` leaves one pocket a single item over, which costs one more move.
+        assert_eq!(plan.cost(), 2);
+        let packed: Rucksack = plan.render(&Letters)[0].parse().unwrap();
+        assert!(packed.shared().is_empty());
+
+        let plan = plan_rucksack("aabbab").expect("a plan");
+        assert_eq!(plan.cost(), 2);
+        assert_eq!(plan.render(&Letters), vec!["aaabbb"]);
+        assert_eq!(
+            plan.moves[0].describe(&Letters),
+            "move 1 a from rucksack 1 right to rucksack 1 left"
+        );
+
+        assert_eq!(plan_rucksack("aaab").err(), Some(NoPlan::Impossible));
+    }
+
+    #[test]
+    fn group() {
+        let group = Group(vec!["aaab".parse().unwrap(), "cdcd".parse().unwrap()]);
+        let packing = packing(&group).unwrap();
+        let plan = plan(&packing, 1_000).expect("a plan");
+        // The first rucksack can't be balanced alone, and a single swap with the second costs
+        // four moves in all; a cycle of moves through both takes three.
+        assert_eq!(plan.cost(), 3);
+        for line in plan.render(&Letters) {
+            let packed: Rucksack = line.parse().unwrap();
+            assert!(packed.shared().is_empty());
+        }
+        assert_eq!(plan.cost(), brute_force(&group).unwrap());
+        assert_eq!(super::plan(&packing, 1).err(), Some(NoPlan::SearchLimit));
+    }
+
+    /// Each pocket of the group, left then right for every rucksack.
+    fn held(group: &Group) -> Vec<ItemCounts> {
+        packing(group).unwrap().into_iter().flatten().collect()
+    }
+
+    /// The fewest moves of any packing keeping the pocket sizes, found by trying them all.
+    fn brute_force(group: &Group) -> Option<u32> {
+        fn fill(
+            pocket: usize,
+            types: &[Item],
+            held: &[ItemCounts],
+            left: &mut ItemCounts,
+            packed: &mut Vec<ItemCounts>,
+            best: &mut Option<u32>,
+        ) {
+            if pocket == held.len() {
+                let apart = packed
+                    .chunks(2)
+                    .all(|p| p[0].iter().all(|(item, _)| p[1].get(item) == 0));
+                let cost = packed
+                    .iter()
+                    .zip(held)
+                    .flat_map(|(p, h)| p.iter().map(|(item, n)| n.saturating_sub(h.get(item))))
+                    .sum();
+                if apart && best.is_none_or(|b| cost < b) {
+                    *best = Some(cost);
+                }
+                return;
+            }
+            let room = held[pocket].total() - packed[pocket].total();
+            let Some((&item, types)) = types.split_first() else {
+                if room == 0 {
+                    let all: Vec<Item> = left.iter().map(|(item, _)| item).collect();
+                    fill(pocket + 1, &all, held, left, packed, best);
+                }
+                return;
+            };
+            for n in 0..=room.min(left.get(item)) {
+                left.take(item, n);
+                packed[pocket].add(item, n);
+                fill(pocket, types, held, left, packed, best);
+                packed[pocket].take(item, n);
+                left.add(item, n);
+            }
+        }
+        let held = held(group);
+        let mut left = held.iter().fold(ItemCounts::new(), |a, p| a.sum(p));
+        let types: Vec<Item> = left.iter().map(|(item, _)| item).collect();
+        let mut packed = vec![ItemCounts::new(); held.len()];
+        let mut best = None;
+        fill(0, &types, &held, &mut left, &mut packed, &mut best);
+        best
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut random = Random::new(3);
+        for _ in 0..300 {
+            let lines: Vec<String> = (0..2 + random.below(2))
+                .map(|_| {
+                    let len = 2 * (1 + random.below(2));
+                    (0..len)
+                        .map(|_| (b'a' + random.below(4) as u8) as char)
+                        .collect()
+                })
+                .collect();
+            let group = Group(lines.iter().map(|l| l.parse().unwrap()).collect());
+            let expected = brute_force(&group);
+            let found = plan(&packing(&group).unwrap(), 100_000);
+            assert_eq!(found.as_ref().ok().map(Plan::cost), expected, "{lines:?}");
+            let Ok(found) = found else {
+                assert_eq!(found.err(), Some(NoPlan::Impossible));
+                continue;
+            };
+            let mut moved = held(&group);
+            for m in &found.moves {
+                let side = |s: Side| s.index();
+                let from = m.from.rucksack * 2 + side(m.from.side);
+                assert_eq!(moved[from].take(m.item, m.count), m.count);
+                moved[m.to.rucksack * 2 + side(m.to.side)].add(m.item, m.count);
+            }
+            let packed: Vec<ItemCounts> = found.packing.iter().flatten().cloned().collect();
+            assert_eq!(moved, packed);
+        }
+    }
+}
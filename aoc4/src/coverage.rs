@@ -0,0 +1,256 @@
+use std::fmt::Display;
+
+use crate::{Error, SectionRange, Team};
+
+/// A run of sections all assigned to the same number of elves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub range: SectionRange,
+    pub depth: usize,
+}
+
+/// How many elves cover each section, as maximal runs of equal depth from the first assigned
+/// section to the last. Runs of depth zero are the gaps.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    ranges: Vec<SectionRange>,
+    segments: Vec<Segment>,
+}
+
+impl Coverage {
+    pub fn new(ranges: Vec<SectionRange>) -> Self {
+        let mut events: Vec<(i64, isize)> = ranges
+            .iter()
+            .flat_map(|r| [(r.start() as i64, 1), (r.end() as i64 + 1, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut segments: Vec<Segment> = vec![];
+        let mut depth = 0;
+        for (i, &(section, change)) in events.iter().enumerate() {
+            depth += change;
+            let Some(&(next, _)) = events.get(i + 1) else {
+                break;
+            };
+            if next == section {
+                continue;
+            }
+            let range =
+                SectionRange::new(section as i32, (next - 1) as i32).expect("events are sorted");
+            match segments.last_mut() {
+                Some(last) if last.depth == depth as usize => {
+                    *last = Segment {
+                        range: last.range.union(&range).expect("segments are adjacent"),
+                        depth: last.depth,
+                    }
+                }
+                _ => segments.push(Segment {
+                    range,
+                    depth: depth as usize,
+                }),
+            }
+        }
+        Self { ranges, segments }
+    }
+
+    /// Every elf of every `2-4,6-8` team line.
+    pub fn from_lines<T: AsRef<str>>(lines: impl Iterator<Item = T>) -> Result<Self, Error> {
+        let mut ranges = vec![];
+        for line in lines {
+            let Team(team): Team = line.as_ref().parse()?;
+            ranges.extend(team);
+        }
+        Ok(Self::new(ranges))
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    fn runs(&self, keep: impl Fn(usize) -> bool) -> Vec<SectionRange> {
+        let mut runs: Vec<SectionRange> = vec![];
+        for segment in self.segments.iter().filter(|s| keep(s.depth)) {
+            match runs.last_mut().and_then(|last| last.union(&segment.range)) {
+                Some(merged) => *runs.last_mut().expect("just matched") = merged,
+                None => runs.push(segment.range),
+            }
+        }
+        runs
+    }
+
+    /// The normalized union of every assignment.
+    pub fn covered(&self) -> Vec<SectionRange> {
+        self.runs(|depth| depth > 0)
+    }
+
+    /// Sections no one is assigned, between the first and last assigned section.
+    pub fn gaps(&self) -> Vec<SectionRange> {
+        self.runs(|depth| depth == 0)
+    }
+
+    pub fn covered_once(&self) -> Vec<SectionRange> {
+        self.runs(|depth| depth == 1)
+    }
+
+    pub fn over_covered(&self) -> Vec<SectionRange> {
+        self.runs(|depth| depth > 1)
+    }
+
+    /// Section assignments beyond the first for each section.
+    pub fn redundant(&self) -> u64 {
+        self.segments
+            .iter()
+            .filter(|s| s.depth > 1)
+            .map(|s| (s.depth as u64 - 1) * s.range.len() as u64)
+            .sum()
+    }
+
+    /// From section 1 (or the first assigned section if lower) to the last assigned section.
+    pub fn span(&self) -> Option<SectionRange> {
+        let first = self.segments.first()?.range.start().min(1);
+        let last = self.segments.last()?.range.end();
+        SectionRange::new(first, last).ok()
+    }
+
+    /// Number of elves on each section of the span, `+` once it passes nine. Spans wider than
+    /// [`STRIP_WIDTH`] are run-length encoded.
+    pub fn depth_strip(&self) -> String {
+        let Some(span) = self.span() else {
+            return String::new();
+        };
+        let first = self.segments[0].range.start();
+        let lead = (first > span.start()).then(|| ('.', (first - span.start()) as u128));
+        let runs = lead.into_iter().chain(self.segments.iter().map(|s| {
+            let symbol = match s.depth {
+                0 => '.',
+                d @ 1..=9 => char::from_digit(d as u32, 10).expect("single digit"),
+                _ => '+',
+            };
+            (symbol, s.range.len())
+        }));
+        match span.len() > STRIP_WIDTH {
+            true => encode(runs),
+            false => runs
+                .flat_map(|(symbol, len)| std::iter::repeat_n(symbol, len as usize))
+                .collect(),
+        }
+    }
+
+    /// The puzzle's per elf diagram, one `.234.....` row per assignment.
+    pub fn diagram(&self) -> Vec<String> {
+        let Some(span) = self.span() else {
+            return vec![];
+        };
+        self.ranges
+            .iter()
+            .map(|r| format!("{}  {r}", strip(r, &span)))
+            .collect()
+    }
+}
+
+/// Widest span drawn section by section.
+pub const STRIP_WIDTH: u128 = 200;
+
+/// `range` drawn over `span`, each covered section shown by its last digit. Spans wider than
+/// [`STRIP_WIDTH`] are run-length encoded, with `#` for the covered sections.
+pub fn strip(range: &SectionRange, span: &SectionRange) -> String {
+    if span.len() > STRIP_WIDTH {
+        let before = (range.start().checked_sub(1))
+            .and_then(|end| SectionRange::new(span.start(), end).ok());
+        let after = (range.end().checked_add(1))
+            .and_then(|start| SectionRange::new(start, span.end()).ok());
+        let runs = [('.', before), ('#', range.intersection(span)), ('.', after)];
+        return encode(
+            runs.into_iter()
+                .filter_map(|(symbol, r)| Some((symbol, r?.len()))),
+        );
+    }
+    (span.start()..=span.end())
+        .map(|section| match range.contains_section(section) {
+            true => char::from_digit(section.unsigned_abs() % 10, 10).expect("single digit"),
+            false => '.',
+        })
+        .collect()
+}
+
+/// Runs of one symbol as `symbol*count`, space separated.
+fn encode(runs: impl Iterator<Item = (char, u128)>) -> String {
+    runs.map(|(symbol, len)| format!("{symbol}*{len}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn list(ranges: &[SectionRange]) -> String {
+    match ranges.is_empty() {
+        true => "none".to_string(),
+        false => ranges
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+impl Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Covered: {}", list(&self.covered()))?;
+        writeln!(f, "Gaps: {}", list(&self.gaps()))?;
+        writeln!(f, "Covered once: {}", list(&self.covered_once()))?;
+        writeln!(f, "Over-covered: {}", list(&self.over_covered()))?;
+        for segment in self.segments.iter().filter(|s| s.depth > 0) {
+            writeln!(f, "  {}: {} elves", segment.range, segment.depth)?;
+        }
+        write!(f, "Redundant section assignments: {}", self.redundant())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Coverage, SectionRange};
+
+    fn ranges(s: &str) -> Vec<SectionRange> {
+        s.split(',').map(|r| r.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn example() {
+        let coverage = Coverage::from_lines(["2-4,6-8,2-3", "4-5"].iter()).unwrap();
+        assert_eq!(
+            coverage.diagram(),
+            vec![
+                ".234....  2-4",
+                ".....678  6-8",
+                ".23.....  2-3",
+                "...45...  4-5"
+            ]
+        );
+        assert_eq!(coverage.depth_strip(), ".2221111");
+        assert_eq!(coverage.covered(), ranges("2-8"));
+        assert_eq!(coverage.gaps(), vec![]);
+        assert_eq!(coverage.over_covered(), ranges("2-4"));
+        assert_eq!(coverage.covered_once(), ranges("5-8"));
+        assert_eq!(coverage.redundant(), 3);
+    }
+
+    #[test]
+    fn gaps() {
+        let coverage = Coverage::new(ranges("3-4,10-12,11-11,20-20"));
+        assert_eq!(coverage.covered(), ranges("3-4,10-12,20-20"));
+        assert_eq!(coverage.gaps(), ranges("5-9,13-19"));
+        assert_eq!(coverage.covered_once(), ranges("3-4,10-10,12-12,20-20"));
+        assert_eq!(coverage.redundant(), 1);
+        assert_eq!(coverage.depth_strip(), "..11.....121.......1");
+        assert!(Coverage::new(vec![]).span().is_none());
+    }
+
+    #[test]
+    fn wide() {
+        let coverage = Coverage::new(ranges("1-2000000000,5-6"));
+        assert_eq!(coverage.depth_strip(), "1*4 2*2 1*1999999994");
+        assert_eq!(
+            coverage.diagram(),
+            vec!["#*2000000000  1-2000000000", ".*4 #*2 .*1999999994  5-6"]
+        );
+        assert_eq!(Coverage::new(ranges("300-310")).depth_strip(), ".*299 1*11");
+        assert_eq!(Coverage::new(ranges("100-200")).depth_strip().len(), 200);
+    }
+}
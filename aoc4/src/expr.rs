@@ -0,0 +1,411 @@
+use std::str::FromStr;
+
+use crate::{Error, Region, Team};
+
+/// One of the two elves a pair expression talks about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Member {
+    L,
+    R,
+}
+
+impl Member {
+    fn index(self) -> usize {
+        match self {
+            Member::L => 0,
+            Member::R => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+/// A parsed team predicate such as `overlap(l, r) and len(l) > 3`.
+///
+/// `l` and `r` are the first two elves on the line. `contains(a, b)`, `overlap(a, b)` and
+/// `len(a)` take them as arguments; `intersection_len`, `union_len` and `members` describe
+/// the whole team. Numbers combine with `+` and `-` and compare with `<`, `<=`, `>`, `>=`,
+/// `==` and `!=`; conditions with `not`, `and` and `or`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr(Node);
+
+/// The tree under an [`Expr`], only ever built by parsing so its types are checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Bool(bool),
+    Number(i128),
+    Contains(Member, Member),
+    Overlap(Member, Member),
+    Len(Member),
+    IntersectionLen,
+    UnionLen,
+    Members,
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Compare(Box<Node>, Comparison, Box<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bool,
+    Number,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(i128),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 11] = ["<=", ">=", "==", "!=", "<", ">", "+", "-", "(", ")", ","];
+
+fn invalid(at: usize, message: impl std::fmt::Display) -> Error {
+    Error::InvalidExpression(format!("{message} at column {}", at + 1))
+}
+
+/// Tokens with the column they start at, and the end of input.
+fn tokenize(s: &str) -> Result<(Vec<(Token, usize)>, usize), Error> {
+    let mut tokens = vec![];
+    let mut rest = s.char_indices().peekable();
+    while let Some(&(at, c)) = rest.peek() {
+        if c.is_whitespace() {
+            rest.next();
+        } else if c.is_ascii_alphabetic() || c == '_' || c.is_ascii_digit() {
+            let mut word = String::new();
+            while let Some(&(_, c)) = rest
+                .peek()
+                .filter(|(_, c)| c.is_alphanumeric() || *c == '_')
+            {
+                word.push(c);
+                rest.next();
+            }
+            let token = match c.is_ascii_digit() {
+                true => Token::Number(word.parse().map_err(|_| invalid(at, "invalid number"))?),
+                false => Token::Ident(word),
+            };
+            tokens.push((token, at));
+        } else {
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| s[at..].starts_with(**symbol))
+                .ok_or_else(|| invalid(at, format!("unexpected `{c}`")))?;
+            for _ in 0..symbol.len() {
+                rest.next();
+            }
+            tokens.push((Token::Symbol(symbol), at));
+        }
+    }
+    Ok((tokens, s.len()))
+}
+
+/// Recursive descent over the tokens, loosest binding first.
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    next: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn at(&self) -> usize {
+        self.tokens.get(self.next).map_or(self.end, |(_, at)| *at)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(token, _)| token)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek() == Some(token);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), Error> {
+        match self.eat(&Token::Symbol(symbol)) {
+            true => Ok(()),
+            false => Err(invalid(self.at(), format!("expected `{symbol}`"))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        self.eat(&Token::Ident(word.to_string()))
+    }
+
+    fn or(&mut self) -> Result<Node, Error> {
+        let mut expr = self.and()?;
+        while self.keyword("or") {
+            expr = Node::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Node, Error> {
+        let mut expr = self.not()?;
+        while self.keyword("and") {
+            expr = Node::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Node, Error> {
+        match self.keyword("not") {
+            true => Ok(Node::Not(Box::new(self.not()?))),
+            false => self.comparison(),
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Node, Error> {
+        let left = self.sum()?;
+        let comparison = match self.peek() {
+            Some(Token::Symbol("<")) => Comparison::Less,
+            Some(Token::Symbol("<=")) => Comparison::LessOrEqual,
+            Some(Token::Symbol(">")) => Comparison::Greater,
+            Some(Token::Symbol(">=")) => Comparison::GreaterOrEqual,
+            Some(Token::Symbol("==")) => Comparison::Equal,
+            Some(Token::Symbol("!=")) => Comparison::NotEqual,
+            _ => return Ok(left),
+        };
+        self.next += 1;
+        Ok(Node::Compare(
+            Box::new(left),
+            comparison,
+            Box::new(self.sum()?),
+        ))
+    }
+
+    fn sum(&mut self) -> Result<Node, Error> {
+        let mut expr = self.atom()?;
+        loop {
+            if self.eat(&Token::Symbol("+")) {
+                expr = Node::Add(Box::new(expr), Box::new(self.atom()?));
+            } else if self.eat(&Token::Symbol("-")) {
+                expr = Node::Sub(Box::new(expr), Box::new(self.atom()?));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn member(&mut self) -> Result<Member, Error> {
+        let at = self.at();
+        let member = match self.peek() {
+            Some(Token::Ident(name)) if name == "l" => Member::L,
+            Some(Token::Ident(name)) if name == "r" => Member::R,
+            _ => return Err(invalid(at, "expected `l` or `r`")),
+        };
+        self.next += 1;
+        Ok(member)
+    }
+
+    fn two_members(&mut self) -> Result<(Member, Member), Error> {
+        self.expect("(")?;
+        let a = self.member()?;
+        self.expect(",")?;
+        let b = self.member()?;
+        self.expect(")")?;
+        Ok((a, b))
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        let at = self.at();
+        let Some((token, _)) = self.tokens.get(self.next).cloned() else {
+            return Err(invalid(at, "unexpected end"));
+        };
+        self.next += 1;
+        match token {
+            Token::Number(n) => Ok(Node::Number(n)),
+            Token::Symbol("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Symbol(symbol) => Err(invalid(at, format!("unexpected `{symbol}`"))),
+            Token::Ident(name) => match name.as_str() {
+                "true" => Ok(Node::Bool(true)),
+                "false" => Ok(Node::Bool(false)),
+                "intersection_len" => Ok(Node::IntersectionLen),
+                "union_len" => Ok(Node::UnionLen),
+                "members" => Ok(Node::Members),
+                "contains" => self.two_members().map(|(a, b)| Node::Contains(a, b)),
+                "overlap" => self.two_members().map(|(a, b)| Node::Overlap(a, b)),
+                "len" => {
+                    self.expect("(")?;
+                    let member = self.member()?;
+                    self.expect(")")?;
+                    Ok(Node::Len(member))
+                }
+                _ => Err(invalid(at, format!("unknown name `{name}`"))),
+            },
+        }
+    }
+}
+
+fn size(cells: u128) -> i128 {
+    i128::try_from(cells).unwrap_or(i128::MAX)
+}
+
+impl Node {
+    /// Whether the expression is a condition or a number, rejecting mixed operands.
+    fn kind(&self) -> Result<Kind, String> {
+        let operands = |a: &Node, b: &Node, kind: Kind, op: &str| match (a.kind()?, b.kind()?)
+            == (kind, kind)
+        {
+            true => Ok(()),
+            false => Err(format!("`{op}` needs two {kind:?} operands")),
+        };
+        match self {
+            Node::Bool(_) | Node::Contains(..) | Node::Overlap(..) => Ok(Kind::Bool),
+            Node::Number(_) | Node::Len(_) => Ok(Kind::Number),
+            Node::IntersectionLen | Node::UnionLen | Node::Members => Ok(Kind::Number),
+            Node::Not(e) => match e.kind()? {
+                Kind::Bool => Ok(Kind::Bool),
+                Kind::Number => Err("`not` needs a condition".to_string()),
+            },
+            Node::And(a, b) => operands(a, b, Kind::Bool, "and").map(|_| Kind::Bool),
+            Node::Or(a, b) => operands(a, b, Kind::Bool, "or").map(|_| Kind::Bool),
+            Node::Add(a, b) => operands(a, b, Kind::Number, "+").map(|_| Kind::Number),
+            Node::Sub(a, b) => operands(a, b, Kind::Number, "-").map(|_| Kind::Number),
+            Node::Compare(a, _, b) => {
+                operands(a, b, Kind::Number, "comparison").map(|_| Kind::Bool)
+            }
+        }
+    }
+
+    fn member<'a, R: Region>(team: &'a Team<R>, member: &Member) -> Result<&'a R, Error> {
+        team.0.get(member.index()).ok_or(Error::MissingMember)
+    }
+
+    fn number<R: Region>(&self, team: &Team<R>) -> Result<i128, Error> {
+        Ok(match self {
+            Node::Number(n) => *n,
+            Node::Len(m) => size(Self::member(team, m)?.size()),
+            Node::IntersectionLen => team.common_intersection().map_or(0, |r| size(r.size())),
+            Node::UnionLen => size(team.union_size()),
+            Node::Members => team.0.len() as i128,
+            Node::Add(a, b) => a.number(team)?.saturating_add(b.number(team)?),
+            Node::Sub(a, b) => a.number(team)?.saturating_sub(b.number(team)?),
+            _ => unreachable!("checked to be a number when parsed"),
+        })
+    }
+
+    fn matches<R: Region>(&self, team: &Team<R>) -> Result<bool, Error> {
+        Ok(match self {
+            Node::Bool(b) => *b,
+            Node::Contains(a, b) => Self::member(team, a)?.contains(Self::member(team, b)?),
+            Node::Overlap(a, b) => Self::member(team, a)?.overlaps(Self::member(team, b)?),
+            Node::Not(e) => !e.matches(team)?,
+            Node::And(a, b) => a.matches(team)? && b.matches(team)?,
+            Node::Or(a, b) => a.matches(team)? || b.matches(team)?,
+            Node::Compare(a, comparison, b) => {
+                let (a, b) = (a.number(team)?, b.number(team)?);
+                match comparison {
+                    Comparison::Less => a < b,
+                    Comparison::LessOrEqual => a <= b,
+                    Comparison::Greater => a > b,
+                    Comparison::GreaterOrEqual => a >= b,
+                    Comparison::Equal => a == b,
+                    Comparison::NotEqual => a != b,
+                }
+            }
+            _ => unreachable!("checked to be a condition when parsed"),
+        })
+    }
+}
+
+impl Expr {
+    /// Evaluates the condition for one team; fails if it names an elf the team doesn't have.
+    pub fn matches<R: Region>(&self, team: &Team<R>) -> Result<bool, Error> {
+        self.0.matches(team)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tokens, end) = tokenize(s)?;
+        let mut parser = Parser {
+            tokens,
+            next: 0,
+            end,
+        };
+        let expr = parser.or()?;
+        if parser.next < parser.tokens.len() {
+            return Err(invalid(parser.at(), "unexpected input"));
+        }
+        match expr.kind().map_err(Error::InvalidExpression)? {
+            Kind::Bool => Ok(Expr(expr)),
+            Kind::Number => Err(Error::InvalidExpression(
+                "expected a condition, not a number".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Expr, Team};
+
+    fn matches(expr: &str, team: &str) -> bool {
+        let expr: Expr = expr.parse().expect("valid expression");
+        expr.matches(&team.parse::<Team>().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn parts() {
+        let part_a = "contains(l, r) or contains(r, l)";
+        assert!(matches(part_a, "2-8,3-7"));
+        assert!(matches(part_a, "6-6,4-6"));
+        assert!(!matches(part_a, "5-7,7-9"));
+        assert!(matches("overlap(l, r)", "5-7,7-9"));
+        assert!(!matches("overlap(l, r)", "2-4,6-8"));
+    }
+
+    #[test]
+    fn numbers() {
+        assert!(matches("overlap(l, r) and len(l) > 3", "2-8,3-7"));
+        assert!(!matches("overlap(l, r) and len(l) > 3", "5-7,7-9"));
+        assert!(matches("intersection_len >= 2", "2-6,4-8"));
+        assert!(!matches("intersection_len >= 2", "5-7,7-9"));
+        assert!(matches(
+            "union_len == len(l) + len(r) - intersection_len",
+            "2-6,4-8"
+        ));
+        assert!(matches("not (members != 3)", "1-1,2-2,3-3"));
+        assert!(matches("(true or false) and not false", "1-1"));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s: &str| match s.parse::<Expr>() {
+            Err(Error::InvalidExpression(message)) => message,
+            other => panic!("expected an error, got {other:?}"),
+        };
+        assert_eq!(error("overlap"), "expected `(` at column 8");
+        assert_eq!(error("len(x) > 1"), "expected `l` or `r` at column 5");
+        assert_eq!(error("contains(l, r) or"), "unexpected end at column 18");
+        assert_eq!(error("len(l) @ 2"), "unexpected `@` at column 8");
+        assert_eq!(error("len(l)"), "expected a condition, not a number");
+        assert_eq!(error("len(l) and true"), "`and` needs two Bool operands");
+        assert_eq!(error("bogus"), "unknown name `bogus` at column 1");
+        let expr: Expr = "overlap(l, r)".parse().unwrap();
+        assert_eq!(
+            expr.matches(&"1-2".parse::<Team>().unwrap()),
+            Err(Error::MissingMember)
+        );
+    }
+}
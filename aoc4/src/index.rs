@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{Error, SectionRange, Team};
+
+/// Every assignment of a file sorted once by start, answering questions across all elves
+/// rather than within a single pair. Elves are identified by their position in the input.
+#[derive(Debug, Clone)]
+pub struct AssignmentIndex {
+    ranges: Vec<SectionRange>,
+    labels: Vec<String>,
+    /// Elf ids ordered by start, ties broken by the longer range first.
+    order: Vec<usize>,
+}
+
+impl AssignmentIndex {
+    pub fn new(ranges: Vec<SectionRange>) -> Self {
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        order.sort_by_key(|&i| (ranges[i].start(), std::cmp::Reverse(ranges[i].end()), i));
+        let labels = (1..=ranges.len()).map(|i| format!("elf {i}")).collect();
+        Self {
+            ranges,
+            labels,
+            order,
+        }
+    }
+
+    /// Every elf of every `2-4,6-8` team line, labelled by line and position.
+    pub fn from_lines<T: AsRef<str>>(lines: impl Iterator<Item = T>) -> Result<Self, Error> {
+        let mut ranges = vec![];
+        let mut labels = vec![];
+        for (i, line) in lines.enumerate() {
+            let Team(team): Team = line.as_ref().parse()?;
+            labels.extend((1..=team.len()).map(|elf| format!("line {} elf {elf}", i + 1)));
+            ranges.extend(team);
+        }
+        Ok(Self {
+            labels,
+            ..Self::new(ranges)
+        })
+    }
+
+    fn describe(&self, elf: usize) -> String {
+        format!("{} ({})", self.labels[elf], self.ranges[elf])
+    }
+
+    pub fn range(&self, elf: usize) -> SectionRange {
+        self.ranges[elf]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Elves sharing at least one section with any other elf, in id order.
+    pub fn overlapping_any(&self) -> Vec<usize> {
+        let mut result = vec![];
+        let mut furthest: Option<i32> = None;
+        for (k, &elf) in self.order.iter().enumerate() {
+            let range = self.ranges[elf];
+            let earlier = furthest.is_some_and(|end| end >= range.start());
+            // The next elf in order has the smallest start of all later ones.
+            let later = self
+                .order
+                .get(k + 1)
+                .is_some_and(|&next| self.ranges[next].start() <= range.end());
+            if earlier || later {
+                result.push(elf);
+            }
+            furthest = furthest.max(Some(range.end()));
+        }
+        result.sort_unstable();
+        result
+    }
+
+    /// Most elves assigned to a single section, and the first section where that happens.
+    pub fn max_depth(&self) -> Option<(usize, i32)> {
+        let mut events: Vec<(i64, i32)> = self
+            .ranges
+            .iter()
+            .flat_map(|r| [(r.start() as i64, 1), (r.end() as i64 + 1, -1)])
+            .collect();
+        events.sort_unstable();
+        let mut depth = 0;
+        let mut best: Option<(usize, i32)> = None;
+        for (i, &(section, change)) in events.iter().enumerate() {
+            depth += change;
+            let last_at_section = events.get(i + 1).is_none_or(|&(next, _)| next != section);
+            if last_at_section && best.is_none_or(|(d, _)| depth as usize > d) {
+                best = Some((depth as usize, section as i32));
+            }
+        }
+        best
+    }
+
+    /// `(outer, inner)` for every pair where `outer` fully contains `inner`. Identical ranges
+    /// are reported once, with the lower id as `outer`.
+    pub fn containments(&self) -> Vec<(usize, usize)> {
+        let mut result = vec![];
+        // Earlier elves in order keyed by end; all of them start at or before the current one.
+        let mut by_end: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+        for &elf in &self.order {
+            let range = self.ranges[elf];
+            for outers in by_end.range(range.end()..).map(|(_, v)| v) {
+                for &outer in outers {
+                    result.push(match self.ranges[outer] == range && elf < outer {
+                        true => (elf, outer),
+                        false => (outer, elf),
+                    });
+                }
+            }
+            by_end.entry(range.end()).or_default().push(elf);
+        }
+        result.sort_unstable();
+        result
+    }
+}
+
+impl Display for AssignmentIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let overlapping = self.overlapping_any();
+        writeln!(
+            f,
+            "Elves overlapping another: {} of {}",
+            overlapping.len(),
+            self.len()
+        )?;
+        for elf in overlapping {
+            writeln!(f, "  {}", self.describe(elf))?;
+        }
+        match self.max_depth() {
+            Some((depth, section)) => {
+                writeln!(f, "Most elves on one section: {depth} at section {section}")?
+            }
+            None => writeln!(f, "Most elves on one section: 0")?,
+        }
+        let pairs = self.containments();
+        write!(f, "Containing pairs: {}", pairs.len())?;
+        for (outer, inner) in pairs {
+            write!(
+                f,
+                "\n  {} contains {}",
+                self.describe(outer),
+                self.describe(inner)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{random::Random, AssignmentIndex, SectionRange};
+
+    fn index(ranges: &[&str]) -> AssignmentIndex {
+        AssignmentIndex::new(ranges.iter().map(|r| r.parse().unwrap()).collect())
+    }
+
+    /// Quadratic versions of the same questions.
+    fn brute_force(ranges: &[SectionRange]) -> (Vec<usize>, usize, Vec<(usize, usize)>) {
+        let n = ranges.len();
+        let overlapping = (0..n)
+            .filter(|&i| (0..n).any(|j| i != j && ranges[i].overlaps(&ranges[j])))
+            .collect();
+        let depth = ranges
+            .iter()
+            .flat_map(|r| [r.start(), r.end()])
+            .map(|s| ranges.iter().filter(|r| r.contains_section(s)).count())
+            .max()
+            .unwrap_or(0);
+        let mut pairs = vec![];
+        for i in 0..n {
+            for j in 0..n {
+                let same = ranges[i] == ranges[j];
+                if i != j && ranges[i].contains(&ranges[j]) && (!same || i < j) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        (overlapping, depth, pairs)
+    }
+
+    #[test]
+    fn example() {
+        let index = index(&[
+            "2-4", "6-8", "2-3", "4-5", "5-7", "7-9", "2-8", "3-7", "6-6", "4-6", "2-6", "4-8",
+        ]);
+        assert_eq!(index.overlapping_any(), (0..12).collect::<Vec<_>>());
+        assert_eq!(index.max_depth(), Some((8, 6)));
+        let (overlapping, depth, pairs) = brute_force(&index.ranges);
+        assert_eq!(index.overlapping_any(), overlapping);
+        assert_eq!(index.max_depth().unwrap().0, depth);
+        assert_eq!(index.containments(), pairs);
+
+        let index = AssignmentIndex::from_lines(["2-4,6-8", "10-14,11-12"].iter()).unwrap();
+        assert_eq!(
+            index.to_string(),
+            "Elves overlapping another: 2 of 4\n  line 2 elf 1 (10-14)\n  line 2 elf 2 (11-12)\n\
+             Most elves on one section: 2 at section 11\n\
+             Containing pairs: 1\n  line 2 elf 1 (10-14) contains line 2 elf 2 (11-12)"
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut random = Random::new(4);
+        for _ in 0..50 {
+            let ranges: Vec<SectionRange> = (0..random.below(30))
+                .map(|_| {
+                    let start = random.below(40) as i32;
+                    SectionRange::new(start, start + random.below(8) as i32).unwrap()
+                })
+                .collect();
+            let index = AssignmentIndex::new(ranges.clone());
+            let (overlapping, depth, pairs) = brute_force(&ranges);
+            assert_eq!(index.overlapping_any(), overlapping);
+            assert_eq!(index.max_depth().map_or(0, |(d, _)| d), depth);
+            assert_eq!(index.containments(), pairs);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+mod coverage;
+mod expr;
+mod index;
+#[cfg(test)]
+mod random;
+mod range;
+mod reallocation;
+mod region;
+mod section;
+mod team;
+
+pub use coverage::{strip, Coverage, Segment, STRIP_WIDTH};
+pub use expr::Expr;
+pub use index::AssignmentIndex;
+pub use range::{parse_ranges, SectionRange};
+pub use reallocation::{reallocate, reallocate_lines, Reallocation, Scope};
+pub use region::{Area, Extent, Region, Volume};
+pub use section::Section;
+pub use team::{count, count_as, Numeric, Predicate, Team};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidDimensions(usize),
+    InvalidExpression(String),
+    InvalidFormat,
+    InvalidNumber(String),
+    InvalidNumeric(String),
+    /// An expression named `l` or `r` on a line with fewer elves.
+    MissingMember,
+    /// More elves than sections in a covered run, so they can't all be kept disjoint.
+    Overcrowded(SectionRange),
+    Reversed {
+        start: String,
+        end: String,
+    },
+    /// A covered run with too many elves and sections to reallocate in reasonable memory.
+    TooLarge(SectionRange),
+}
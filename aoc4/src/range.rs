@@ -0,0 +1,162 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Error, Section};
+
+/// Sections `start` through `end`, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SectionRange<N: Section = i32> {
+    start: N,
+    end: N,
+}
+
+impl<N: Section> SectionRange<N> {
+    pub fn new(start: N, end: N) -> Result<Self, Error> {
+        if start > end {
+            return Err(Error::Reversed {
+                start: start.to_string(),
+                end: end.to_string(),
+            });
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn start(&self) -> N {
+        self.start
+    }
+
+    pub fn end(&self) -> N {
+        self.end
+    }
+
+    /// Number of sections covered.
+    pub fn len(&self) -> u128 {
+        N::count(self.start, self.end)
+    }
+
+    /// Always false, every valid range covers at least one section.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn contains_section(&self, section: N) -> bool {
+        self.start <= section && section <= self.end
+    }
+
+    /// True when every section of `other` is also in `self`.
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Self::new(self.start.max(other.start), self.end.min(other.end)).ok()
+    }
+
+    /// The single range covering both, if they overlap or are adjacent.
+    pub fn union(&self, other: &Self) -> Option<Self> {
+        let (first, second) = match self.start <= other.start {
+            true => (self, other),
+            false => (other, self),
+        };
+        if first.end.next().is_some_and(|next| next < second.start) {
+            return None;
+        }
+        Self::new(first.start, first.end.max(second.end)).ok()
+    }
+}
+
+impl<N: Section> FromStr for SectionRange<N> {
+    type Err = Error;
+
+    /// Parses `2-4`, or `-5--2` for negative sections.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // A leading minus belongs to the start, the separator is the next one.
+        let split = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .ok_or(Error::InvalidFormat)?;
+        let (l, r) = (&s[..split.0], &s[split.0 + 1..]);
+        let number = |n: &str| n.parse().map_err(|_| Error::InvalidNumber(n.to_string()));
+        Self::new(number(l)?, number(r)?)
+    }
+}
+
+impl<N: Section> Display for SectionRange<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+/// Parses an elf pair like `2-4,6-8`.
+pub fn parse_ranges(s: &str) -> Result<(SectionRange, SectionRange), Error> {
+    let (l, r) = s.split_once(',').ok_or(Error::InvalidFormat)?;
+    Ok((l.parse()?, r.parse()?))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{parse_ranges, Error, SectionRange};
+
+    fn range(s: &str) -> SectionRange {
+        s.parse().expect("valid range")
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(range("2-4"), SectionRange::new(2, 4).unwrap());
+        assert_eq!(range("6-6").len(), 1);
+        assert_eq!(range("2-8").to_string(), "2-8");
+        assert_eq!(
+            "4-2".parse::<SectionRange>(),
+            Err(Error::Reversed {
+                start: "4".to_string(),
+                end: "2".to_string()
+            })
+        );
+        assert_eq!("4".parse::<SectionRange>(), Err(Error::InvalidFormat));
+        assert_eq!(
+            "4-x".parse::<SectionRange>(),
+            Err(Error::InvalidNumber("x".to_string()))
+        );
+        assert_eq!(parse_ranges("2-4,6-8"), Ok((range("2-4"), range("6-8"))));
+        assert_eq!(parse_ranges("2-4 6-8"), Err(Error::InvalidFormat));
+    }
+
+    #[test]
+    fn relations() {
+        let (a, b, c) = (range("2-8"), range("3-7"), range("7-9"));
+        assert!(a.contains(&b));
+        assert!(!b.contains(&a));
+        assert!(a.overlaps(&c) && c.overlaps(&a));
+        assert!(!range("2-4").overlaps(&range("5-6")));
+        assert!(a.contains_section(8) && !a.contains_section(9));
+        assert_eq!(a.intersection(&c), Some(range("7-8")));
+        assert_eq!(b.intersection(&range("8-9")), None);
+        assert_eq!(a.union(&c), Some(range("2-9")));
+        assert_eq!(range("2-4").union(&range("5-6")), Some(range("2-6")));
+        assert_eq!(range("2-4").union(&range("6-6")), None);
+        assert_eq!(a.len(), 7);
+    }
+
+    #[test]
+    fn numeric_types() {
+        let negative: SectionRange<i64> = "-5--2".parse().unwrap();
+        assert_eq!(
+            (negative.start(), negative.end(), negative.len()),
+            (-5, -2, 4)
+        );
+        assert!(negative.overlaps(&"-2-3".parse().unwrap()));
+        let full: SectionRange<u8> = "0-255".parse().unwrap();
+        assert_eq!(full.len(), 256);
+        assert!(full.contains(&"7-9".parse().unwrap()));
+        assert_eq!(full.union(&"255-255".parse().unwrap()), Some(full));
+        assert!("0-256".parse::<SectionRange<u8>>().is_err());
+        let huge: SectionRange<u128> = "0-340282366920938463463374607431768211455".parse().unwrap();
+        assert_eq!(huge.len(), u128::MAX);
+    }
+}
@@ -0,0 +1,388 @@
+use std::fmt::Display;
+
+use crate::{Coverage, Error, SectionRange, Team};
+
+/// Which elves must end up with disjoint assignments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// Only elves on the same line.
+    Pair,
+    /// Every elf in the file.
+    File,
+}
+
+/// Rewritten assignments, line for line, and how many sections changed hands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reallocation {
+    pub teams: Vec<Team>,
+    pub moved: u64,
+    /// Whether every run was tried with its elves in every order, so `moved` is the fewest
+    /// possible. Runs too large for that keep their elves in order by start.
+    pub exact: bool,
+}
+
+/// Stands in for an impossible split in the cost tables.
+const INF: i64 = i64::MAX / 4;
+
+/// The most cut table entries a run may need, keeping memory in bounds.
+const LIMIT: usize = 50_000_000;
+
+/// The most cost table entries, one per set of elves and section, for trying a run's elves in
+/// every order.
+const EXACT: usize = 1 << 22;
+
+/// Cheapest costs with one more elf, whose old sections are `a..b` within the run, taking the
+/// piece `q..p` after the earlier elves, for every end `p` along with the `q` chosen; `before[q]`
+/// is the cheapest split of the earlier elves over the first `q` sections. For a fixed `p` the
+/// piece's cost is linear in `q` on each side of `a` and `b`, so a running minimum over each
+/// stretch gives every `p` in constant time. Ties go to the smallest `q`.
+fn extend(before: &[i64], a: usize, b: usize) -> Vec<(i64, usize)> {
+    let len = before.len() - 1;
+    let size = (b - a) as i64;
+    let mut best = vec![(INF, 0); len + 1];
+    // Minimum of before[q] - q for q <= a, before[q] + q for a < q < b, before[q] - q for q >= b.
+    let (mut left, mut middle, mut right) = ((INF, 0), (INF, 0), (INF, 0));
+    for p in 1..=len {
+        let q = p - 1;
+        if before[q] < INF {
+            let stretch = match q {
+                _ if q <= a => (&mut left, before[q] - q as i64),
+                _ if q < b => (&mut middle, before[q] + q as i64),
+                _ => (&mut right, before[q] - q as i64),
+            };
+            if stretch.1 < stretch.0 .0 {
+                *stretch.0 = (stretch.1, q);
+            }
+        }
+        let (p, end) = (p as i64, p.min(b) as i64);
+        let candidates = [
+            (left, size + p - 2 * (end - a as i64).max(0)),
+            (middle, size + p - 2 * end),
+            (right, size + p),
+        ];
+        for ((key, q), rest) in candidates {
+            if key < INF && key + rest < best[p as usize].0 {
+                best[p as usize] = (key + rest, q);
+            }
+        }
+    }
+    best
+}
+
+/// The pieces `q..p` of a run `len` sections long, one per elf in the given order, that move
+/// the fewest sections, along with how many they move.
+fn in_order(olds: &[(usize, usize)], len: usize) -> (i64, Vec<(usize, usize)>) {
+    // cost[p]: cheapest split of the elves so far over the first p sections, and for each elf
+    // where its piece starts given where it ends.
+    let mut cost = vec![INF; len + 1];
+    cost[0] = 0;
+    let mut cut = Vec::with_capacity(olds.len());
+    for &(a, b) in olds {
+        let best = extend(&cost, a, b);
+        cost = best.iter().map(|&(c, _)| c).collect();
+        cut.push(best.into_iter().map(|(_, q)| q).collect::<Vec<usize>>());
+    }
+    let mut pieces = vec![(0, 0); olds.len()];
+    let mut p = len;
+    for (i, cut) in cut.iter().enumerate().rev() {
+        pieces[i] = (cut[p], p);
+        p = cut[p];
+    }
+    (cost[len], pieces)
+}
+
+/// The pieces `q..p` of a run `len` sections long, one per elf in whichever order moves the
+/// fewest sections, along with how many they move. `cost[set][p]` is the cheapest split of a
+/// set of elves over the first `p` sections; sets are numbered so each comes after its subsets.
+fn any_order(olds: &[(usize, usize)], len: usize) -> (i64, Vec<(usize, usize)>) {
+    let mut cost = vec![vec![INF; len + 1]; 1 << olds.len()];
+    cost[0][0] = 0;
+    for set in 0..cost.len() - 1 {
+        for (elf, &(a, b)) in olds.iter().enumerate() {
+            if set & 1 << elf != 0 {
+                continue;
+            }
+            let best = extend(&cost[set], a, b);
+            for (c, (new, _)) in cost[set | 1 << elf].iter_mut().zip(best) {
+                *c = (*c).min(new);
+            }
+        }
+    }
+    // Walk back, taking the last piece from the latest starting elf that can have it.
+    let mut pieces = vec![(0, 0); olds.len()];
+    let (mut set, mut p) = (cost.len() - 1, len);
+    while set != 0 {
+        let (elf, q) = (0..olds.len())
+            .rev()
+            .filter(|elf| set & 1 << elf != 0)
+            .find_map(|elf| {
+                let (a, b) = olds[elf];
+                let (c, q) = extend(&cost[set ^ 1 << elf], a, b)[p];
+                (c == cost[set][p]).then_some((elf, q))
+            })
+            .expect("some elf took the last piece");
+        pieces[elf] = (q, p);
+        (set, p) = (set ^ 1 << elf, q);
+    }
+    (cost[cost.len() - 1][len], pieces)
+}
+
+/// Disjoint ranges, one per elf, covering exactly the sections covered before and moving as
+/// few sections as possible, with how many moved and whether that is the fewest possible. Each
+/// covered run is cut into consecutive pieces by dynamic programming over the elves in every
+/// order. A run with too many elves for that keeps them in order by start instead, in time
+/// proportional to elves times sections, which may move more than needed when one elf's
+/// range holds another's.
+pub fn reallocate(ranges: &[SectionRange]) -> Result<(Vec<SectionRange>, u64, bool), Error> {
+    let mut result = ranges.to_vec();
+    let mut total = 0;
+    let mut exact = true;
+    for run in Coverage::new(ranges.to_vec()).covered() {
+        let mut elves: Vec<usize> = (0..ranges.len())
+            .filter(|&i| run.contains(&ranges[i]))
+            .collect();
+        elves.sort_by_key(|&i| (ranges[i], i));
+        let (k, len) = (elves.len(), run.len() as usize);
+        if k > len {
+            return Err(Error::Overcrowded(run));
+        }
+        // Elves already apart keep their sections.
+        if elves
+            .windows(2)
+            .all(|w| ranges[w[0]].end() < ranges[w[1]].start())
+        {
+            continue;
+        }
+        let olds: Vec<(usize, usize)> = elves
+            .iter()
+            .map(|&elf| {
+                let a = (ranges[elf].start() - run.start()) as usize;
+                (a, (ranges[elf].end() - run.start()) as usize + 1)
+            })
+            .collect();
+        let sets = 1usize.checked_shl(k as u32);
+        let (cost, pieces) = match sets.and_then(|n| n.checked_mul(len + 1)) {
+            Some(entries) if entries <= EXACT => any_order(&olds, len),
+            _ if k.saturating_mul(len + 1) <= LIMIT => {
+                exact = false;
+                in_order(&olds, len)
+            }
+            _ => return Err(Error::TooLarge(run)),
+        };
+        total += cost as u64;
+        for (&elf, (q, p)) in elves.iter().zip(pieces) {
+            result[elf] = SectionRange::new(run.start() + q as i32, run.start() + p as i32 - 1)
+                .expect("pieces are not empty");
+        }
+    }
+    Ok((result, total, exact))
+}
+
+/// Reallocates every `2-4,6-8` team line within the given scope.
+pub fn reallocate_lines<T: AsRef<str>>(
+    lines: impl Iterator<Item = T>,
+    scope: Scope,
+) -> Result<Reallocation, Error> {
+    let teams = lines
+        .map(|l| l.as_ref().parse())
+        .collect::<Result<Vec<Team>, _>>()?;
+    match scope {
+        Scope::Pair => {
+            let mut moved = 0;
+            let mut exact = true;
+            let mut result = vec![];
+            for Team(team) in teams {
+                let (ranges, cost, all_orders) = reallocate(&team)?;
+                moved += cost;
+                exact &= all_orders;
+                result.push(Team(ranges));
+            }
+            Ok(Reallocation {
+                teams: result,
+                moved,
+                exact,
+            })
+        }
+        Scope::File => {
+            let all: Vec<SectionRange> = teams.iter().flat_map(|t| t.0.clone()).collect();
+            let (mut ranges, moved, exact) = reallocate(&all)?;
+            let teams = teams
+                .iter()
+                .map(|t| Team(ranges.drain(..t.0.len()).collect()))
+                .collect();
+            Ok(Reallocation {
+                teams,
+                moved,
+                exact,
+            })
+        }
+    }
+}
+
+impl Display for Reallocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for team in &self.teams {
+            writeln!(f, "{team}")?;
+        }
+        write!(f, "Sections moved: {}", self.moved)?;
+        if !self.exact {
+            write!(
+                f,
+                " (elves kept in order in large runs, may not be the fewest)"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        random::Random, reallocate, reallocate_lines, Coverage, Error, Scope, SectionRange,
+    };
+
+    fn ranges(s: &str) -> Vec<SectionRange> {
+        s.split(',').map(|r| r.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn pairs() {
+        let input = [
+            "2-4,6-8", "2-3,4-5", "5-7,7-9", "2-8,3-7", "6-6,4-6", "2-6,4-8",
+        ];
+        let result = reallocate_lines(input.iter(), Scope::Pair).unwrap();
+        assert_eq!(
+            result.to_string(),
+            "2-4,6-8\n2-3,4-5\n5-6,7-9\n2-2,3-8\n6-6,4-5\n2-3,4-8\nSections moved: 12"
+        );
+        assert_eq!(
+            reallocate(&ranges("5-5,5-5")),
+            Err(Error::Overcrowded(ranges("5-5")[0]))
+        );
+    }
+
+    #[test]
+    fn whole_file() {
+        let input = ["2-4,6-8", "2-3,4-5"];
+        let result = reallocate_lines(input.iter(), Scope::File).unwrap();
+        let all: Vec<SectionRange> = result.teams.iter().flat_map(|t| t.0.clone()).collect();
+        let coverage = Coverage::new(all);
+        assert_eq!(coverage.covered(), ranges("2-8"));
+        assert_eq!(coverage.over_covered(), vec![]);
+        assert_eq!(result.to_string(), "3-3,6-8\n2-2,4-5\nSections moved: 3");
+    }
+
+    /// The cheapest total for one run with the elves in the given order, section by section.
+    fn by_sections(run: SectionRange, ranges: &[SectionRange]) -> u64 {
+        let (k, len) = (ranges.len(), run.len() as usize);
+        let moved = |old: &SectionRange, from: usize, to: usize| {
+            let new =
+                SectionRange::new(run.start() + from as i32, run.start() + to as i32 - 1).unwrap();
+            let shared = old.intersection(&new).map_or(0, |r| r.len() as u64);
+            old.len() as u64 + new.len() as u64 - 2 * shared
+        };
+        let mut cost = vec![vec![u64::MAX; len + 1]; k + 1];
+        cost[0][0] = 0;
+        for i in 1..=k {
+            for p in i..=len - (k - i) {
+                for q in i - 1..p {
+                    if cost[i - 1][q] != u64::MAX {
+                        let c = cost[i - 1][q] + moved(&ranges[i - 1], q, p);
+                        cost[i][p] = cost[i][p].min(c);
+                    }
+                }
+            }
+        }
+        cost[k][len]
+    }
+
+    /// The cheapest of `by_sections` over every order of the elves.
+    fn every_order(run: SectionRange, ranges: &mut [SectionRange], placed: usize) -> u64 {
+        if placed == ranges.len() {
+            return by_sections(run, ranges);
+        }
+        (placed..ranges.len())
+            .map(|i| {
+                ranges.swap(placed, i);
+                let cost = every_order(run, ranges, placed + 1);
+                ranges.swap(placed, i);
+                cost
+            })
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn nested() {
+        // Keeping the elves in order by start moves 10 sections; letting 8-14 go after 9-12,
+        // as in 4-6,7-8,13-14,9-12, moves 8.
+        let team = ranges("4-6,5-9,8-14,9-12");
+        let (result, moved, exact) = reallocate(&team).unwrap();
+        assert_eq!((moved, exact), (8, true));
+        let coverage = Coverage::new(result);
+        assert_eq!(coverage.covered(), ranges("4-14"));
+        assert_eq!(coverage.over_covered(), vec![]);
+    }
+
+    #[test]
+    fn matches_every_order() {
+        let mut random = Random::new(37);
+        for _ in 0..500 {
+            let elves = 1 + random.below(5);
+            let team: Vec<SectionRange> = (0..elves)
+                .map(|_| {
+                    let start = random.below(20) as i32;
+                    SectionRange::new(start, start + random.below(8) as i32).unwrap()
+                })
+                .collect();
+            let Ok((result, moved, exact)) = reallocate(&team) else {
+                continue;
+            };
+            assert!(exact);
+            let expected: u64 = Coverage::new(team.clone())
+                .covered()
+                .into_iter()
+                .map(|run| {
+                    let mut inside: Vec<SectionRange> =
+                        team.iter().filter(|r| run.contains(r)).copied().collect();
+                    every_order(run, &mut inside, 0)
+                })
+                .sum();
+            assert_eq!(moved, expected, "{team:?}");
+            let coverage = Coverage::new(result.clone());
+            assert_eq!(coverage.covered(), Coverage::new(team.clone()).covered());
+            assert_eq!(coverage.over_covered(), vec![]);
+            let changed: u64 = team
+                .iter()
+                .zip(&result)
+                .map(|(old, new)| {
+                    let shared = old.intersection(new).map_or(0, |r| r.len() as u64);
+                    (old.len() + new.len()) as u64 - 2 * shared
+                })
+                .sum();
+            assert_eq!(changed, moved, "{team:?}");
+        }
+    }
+
+    #[test]
+    fn long_runs() {
+        let result = reallocate_lines(["1-200000,5-6"].iter(), Scope::Pair).unwrap();
+        assert_eq!(
+            result.to_string(),
+            "1-199999,200000-200000\nSections moved: 4"
+        );
+        let apart = ranges("1-60000000,70000000-70000001");
+        assert_eq!(reallocate(&apart), Ok((apart.clone(), 0, true)));
+        let crowd: Vec<SectionRange> = (0..30)
+            .map(|i| SectionRange::new(1 + i, 100 + i).unwrap())
+            .collect();
+        let (result, _, exact) = reallocate(&crowd).unwrap();
+        assert!(!exact);
+        assert_eq!(Coverage::new(result).over_covered(), vec![]);
+        let crowd: Vec<SectionRange> = (0..1000).map(|_| ranges("1-100000")[0]).collect();
+        assert_eq!(
+            reallocate(&crowd),
+            Err(Error::TooLarge(ranges("1-100000")[0]))
+        );
+    }
+}
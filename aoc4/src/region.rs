@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Error, Section, SectionRange};
+
+/// Something an elf can be assigned: a run of sections, an area or a volume.
+pub trait Region: Sized + Clone + Display + FromStr<Err = Error> {
+    /// True when every cell of `other` is also in `self`.
+    fn contains(&self, other: &Self) -> bool;
+
+    fn overlaps(&self, other: &Self) -> bool;
+
+    fn intersection(&self, other: &Self) -> Option<Self>;
+
+    /// Number of cells covered.
+    fn size(&self) -> u128;
+
+    /// Number of cells covered by at least one of `regions`.
+    fn union_size(regions: &[Self]) -> u128;
+}
+
+impl<N: Section> Region for SectionRange<N> {
+    fn contains(&self, other: &Self) -> bool {
+        SectionRange::contains(self, other)
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        SectionRange::overlaps(self, other)
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        SectionRange::intersection(self, other)
+    }
+
+    fn size(&self) -> u128 {
+        self.len()
+    }
+
+    fn union_size(regions: &[Self]) -> u128 {
+        let mut sorted = regions.to_vec();
+        sorted.sort_unstable();
+        let mut merged: Vec<Self> = vec![];
+        for range in sorted {
+            match merged.last().and_then(|last| last.union(&range)) {
+                Some(union) => *merged.last_mut().expect("just matched") = union,
+                None => merged.push(range),
+            }
+        }
+        merged
+            .iter()
+            .fold(0, |total, r| total.saturating_add(r.len()))
+    }
+}
+
+/// An axis-aligned box of `D` section ranges, `1-3x2-5` in two dimensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Extent<const D: usize, N: Section = i32>(pub [SectionRange<N>; D]);
+
+pub type Area<N = i32> = Extent<2, N>;
+pub type Volume<N = i32> = Extent<3, N>;
+
+impl<const D: usize, N: Section> Region for Extent<D, N> {
+    fn contains(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a.contains(b))
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a.overlaps(b))
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        let mut axes = self.0;
+        for (axis, b) in axes.iter_mut().zip(&other.0) {
+            *axis = axis.intersection(b)?;
+        }
+        Some(Self(axes))
+    }
+
+    fn size(&self) -> u128 {
+        self.0
+            .iter()
+            .fold(1, |total: u128, r| total.saturating_mul(r.len()))
+    }
+
+    /// Splits every axis at each box boundary and adds up the cells inside some box, which is
+    /// fine for the handful of boxes on one line.
+    fn union_size(regions: &[Self]) -> u128 {
+        let Some(&Self(mut cell)) = regions.first() else {
+            return 0;
+        };
+        // Per axis, the slices between consecutive boundaries; each lies wholly inside or
+        // outside every box.
+        let slices: Vec<Vec<SectionRange<N>>> = (0..D)
+            .map(|axis| {
+                let last = regions.iter().map(|r| r.0[axis].end()).max();
+                let last = last.expect("at least one region");
+                let mut cuts: Vec<N> = regions
+                    .iter()
+                    .flat_map(|r| [Some(r.0[axis].start()), r.0[axis].end().next()])
+                    .flatten()
+                    .collect();
+                cuts.sort_unstable();
+                cuts.dedup();
+                cuts.iter()
+                    .enumerate()
+                    .filter_map(|(i, &from)| {
+                        let to = match cuts.get(i + 1) {
+                            Some(&next) => next.prev().expect("after a smaller cut"),
+                            None => last,
+                        };
+                        SectionRange::new(from, to).ok()
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut total: u128 = 0;
+        let mut digits = [0; D];
+        'cells: loop {
+            for axis in 0..D {
+                cell[axis] = slices[axis][digits[axis]];
+            }
+            let cell = Self(cell);
+            if regions.iter().any(|r| r.contains(&cell)) {
+                total = total.saturating_add(cell.size());
+            }
+            for axis in 0..D {
+                digits[axis] += 1;
+                if digits[axis] < slices[axis].len() {
+                    continue 'cells;
+                }
+                digits[axis] = 0;
+            }
+            break;
+        }
+        total
+    }
+}
+
+impl<const D: usize, N: Section> FromStr for Extent<D, N> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let axes: Vec<SectionRange<N>> = s
+            .trim()
+            .split('x')
+            .map(|axis| axis.parse())
+            .collect::<Result<_, _>>()?;
+        axes.try_into().map(Self).map_err(|_| Error::InvalidFormat)
+    }
+}
+
+impl<const D: usize, N: Section> Display for Extent<D, N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let axes: Vec<String> = self.0.iter().map(|r| r.to_string()).collect();
+        write!(f, "{}", axes.join("x"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Area, Region, SectionRange, Volume};
+
+    fn area(s: &str) -> Area {
+        s.parse().expect("valid area")
+    }
+
+    #[test]
+    fn areas() {
+        let (a, b) = (area("1-3x2-5"), area("2-3x3-4"));
+        assert!(a.contains(&b) && !b.contains(&a));
+        assert_eq!(a.size(), 12);
+        assert_eq!(a.to_string(), "1-3x2-5");
+        assert!(a.overlaps(&area("3-9x5-5")));
+        assert!(!a.overlaps(&area("4-9x2-5")));
+        assert_eq!(a.intersection(&area("3-9x0-2")), Some(area("3-3x2-2")));
+        assert_eq!(Area::union_size(&[a, b]), 12);
+        assert_eq!(Area::union_size(&[a, area("3-4x5-6")]), 12 + 4 - 1);
+        assert!("1-3".parse::<Area>().is_err());
+        assert!("1-3x2-5x1-1".parse::<Area>().is_err());
+    }
+
+    #[test]
+    fn volumes() {
+        let a: Volume<u8> = "0-9x0-9x0-9".parse().unwrap();
+        let b: Volume<u8> = "5-14x5-14x5-14".parse().unwrap();
+        assert_eq!(a.intersection(&b).unwrap().size(), 125);
+        assert_eq!(Volume::union_size(&[a, b]), 2000 - 125);
+        let full: SectionRange<u8> = "0-255".parse().unwrap();
+        assert_eq!(
+            SectionRange::union_size(&[full, "3-4".parse().unwrap()]),
+            256
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::{fmt::Debug, fmt::Display, hash::Hash, str::FromStr};
+
+/// An integer type section numbers can be read as.
+pub trait Section: Copy + Ord + Hash + Debug + Display + FromStr {
+    /// Sections `start` through `end`, both included, saturating at `u128::MAX`.
+    fn count(start: Self, end: Self) -> u128;
+
+    /// The following section, unless `self` is the largest value.
+    fn next(self) -> Option<Self>;
+
+    /// The preceding section, unless `self` is the smallest value.
+    fn prev(self) -> Option<Self>;
+}
+
+macro_rules! section {
+    ($($t:ty),*) => {
+        $(
+            impl Section for $t {
+                fn count(start: Self, end: Self) -> u128 {
+                    (start.abs_diff(end) as u128).saturating_add(1)
+                }
+
+                fn next(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn prev(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+            }
+        )*
+    };
+}
+
+section!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
@@ -0,0 +1,234 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Area, Error, Expr, Region, Section, SectionRange, Volume};
+
+/// Every elf assigned on one line, `2-4,6-8,3-5` is a team of three.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Team<R: Region = SectionRange>(pub Vec<R>);
+
+impl<R: Region> Team<R> {
+    fn pairs(&self) -> impl Iterator<Item = (&R, &R)> {
+        self.0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, l)| self.0[i + 1..].iter().map(move |r| (l, r)))
+    }
+
+    pub fn any_overlap(&self) -> bool {
+        self.pairs().any(|(l, r)| l.overlaps(r))
+    }
+
+    pub fn any_containment(&self) -> bool {
+        self.pairs().any(|(l, r)| l.contains(r) || r.contains(l))
+    }
+
+    /// Sections assigned to every member.
+    pub fn common_intersection(&self) -> Option<R> {
+        let (first, rest) = self.0.split_first()?;
+        rest.iter()
+            .try_fold(first.clone(), |acc, r| acc.intersection(r))
+    }
+
+    /// Sections assigned to at least one member.
+    pub fn union_size(&self) -> u128 {
+        R::union_size(&self.0)
+    }
+}
+
+impl<R: Region> FromStr for Team<R> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|r| r.parse())
+            .collect::<Result<_, _>>()
+            .map(Team)
+    }
+}
+
+impl<R: Region> Display for Team<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<String> = self.0.iter().map(|r| r.to_string()).collect();
+        write!(f, "{}", ranges.join(","))
+    }
+}
+
+/// Which teams [`count`] counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// Some two members share a section.
+    AnyOverlap,
+    /// Some member's range fully contains another's.
+    AnyContainment,
+    /// At least one section is assigned to every member.
+    CommonIntersection,
+    /// The members together cover no more than this many sections.
+    UnionAtMost(u128),
+    /// Any condition written in the [`Expr`] language.
+    Expression(Expr),
+}
+
+impl Predicate {
+    pub fn matches<R: Region>(&self, team: &Team<R>) -> Result<bool, Error> {
+        Ok(match self {
+            Predicate::AnyOverlap => team.any_overlap(),
+            Predicate::AnyContainment => team.any_containment(),
+            Predicate::CommonIntersection => team.common_intersection().is_some(),
+            Predicate::UnionAtMost(size) => team.union_size() <= *size,
+            Predicate::Expression(expr) => expr.matches(team)?,
+        })
+    }
+}
+
+/// Parses `any-overlap`, `any-containment`, `common-intersection`, `union-at-most:N` or
+/// otherwise an expression like `contains(l, r) or contains(r, l)`.
+impl FromStr for Predicate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "any-overlap" => Ok(Predicate::AnyOverlap),
+            None if s == "any-containment" => Ok(Predicate::AnyContainment),
+            None if s == "common-intersection" => Ok(Predicate::CommonIntersection),
+            Some(("union-at-most", size)) => size
+                .parse()
+                .map(Predicate::UnionAtMost)
+                .map_err(|_| Error::InvalidNumber(size.to_string())),
+            _ => s.parse().map(Predicate::Expression),
+        }
+    }
+}
+
+/// Number of team lines matching the predicate.
+pub fn count<R: Region, T: AsRef<str>>(
+    lines: impl Iterator<Item = T>,
+    predicate: &Predicate,
+) -> Result<usize, Error> {
+    let mut count = 0;
+    for line in lines {
+        if predicate.matches(&line.as_ref().parse::<Team<R>>()?)? {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// The integer types [`count_as`] can read sections as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numeric {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I32,
+    I64,
+}
+
+impl FromStr for Numeric {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "u8" => Ok(Numeric::U8),
+            "u16" => Ok(Numeric::U16),
+            "u32" => Ok(Numeric::U32),
+            "u64" => Ok(Numeric::U64),
+            "u128" => Ok(Numeric::U128),
+            "i32" => Ok(Numeric::I32),
+            "i64" => Ok(Numeric::I64),
+            _ => Err(Error::InvalidNumeric(s.to_string())),
+        }
+    }
+}
+
+fn count_dims<N: Section, T: AsRef<str>>(
+    lines: impl Iterator<Item = T>,
+    predicate: &Predicate,
+    dims: usize,
+) -> Result<usize, Error> {
+    match dims {
+        1 => count::<SectionRange<N>, T>(lines, predicate),
+        2 => count::<Area<N>, T>(lines, predicate),
+        3 => count::<Volume<N>, T>(lines, predicate),
+        _ => Err(Error::InvalidDimensions(dims)),
+    }
+}
+
+/// [`count`] with sections read as `numeric` over `dims` axes, both picked at runtime.
+pub fn count_as<T: AsRef<str>>(
+    lines: impl Iterator<Item = T>,
+    predicate: &Predicate,
+    numeric: Numeric,
+    dims: usize,
+) -> Result<usize, Error> {
+    match numeric {
+        Numeric::U8 => count_dims::<u8, T>(lines, predicate, dims),
+        Numeric::U16 => count_dims::<u16, T>(lines, predicate, dims),
+        Numeric::U32 => count_dims::<u32, T>(lines, predicate, dims),
+        Numeric::U64 => count_dims::<u64, T>(lines, predicate, dims),
+        Numeric::U128 => count_dims::<u128, T>(lines, predicate, dims),
+        Numeric::I32 => count_dims::<i32, T>(lines, predicate, dims),
+        Numeric::I64 => count_dims::<i64, T>(lines, predicate, dims),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{count, count_as, Error, Numeric, Predicate, SectionRange, Team};
+
+    fn team(s: &str) -> Team {
+        s.parse().expect("valid team")
+    }
+
+    #[test]
+    fn teams() {
+        let t = team("2-4,6-8,3-5");
+        assert!(t.any_overlap());
+        assert!(!t.any_containment());
+        assert_eq!(t.common_intersection(), None);
+        assert_eq!(t.union_size(), 7);
+        let t = team("1-9,3-5,4-6");
+        assert!(t.any_containment());
+        assert_eq!(t.common_intersection(), Some("4-5".parse().unwrap()));
+        assert_eq!(t.union_size(), 9);
+        assert_eq!(
+            team("3-5").common_intersection(),
+            Some("3-5".parse().unwrap())
+        );
+        assert!("2-4,".parse::<Team>().is_err());
+    }
+
+    #[test]
+    fn predicates() {
+        let lines = ["2-4,6-8,3-5", "1-9,3-5,4-6", "1-1,3-3,5-5", "2-4,6-8"];
+        let counted =
+            |p: &str| count::<SectionRange, _>(lines.iter(), &p.parse().unwrap()).unwrap();
+        assert_eq!(counted("any-overlap"), 2);
+        assert_eq!(counted("any-containment"), 1);
+        assert_eq!(counted("common-intersection"), 1);
+        assert_eq!(counted("union-at-most:6"), 2);
+        assert!("overlap".parse::<Predicate>().is_err());
+        assert_eq!(counted("contains(l, r) or contains(r, l)"), 1);
+        assert_eq!(counted("overlap(l, r) and members == 2"), 0);
+    }
+
+    #[test]
+    fn numeric_and_dimensions() {
+        let overlap = Predicate::AnyOverlap;
+        let negative = ["-5--2,-2-3", "-9--7,7-9"];
+        assert_eq!(count_as(negative.iter(), &overlap, Numeric::I64, 1), Ok(1));
+        assert!(count_as(negative.iter(), &overlap, Numeric::U8, 1).is_err());
+        let areas = ["1-3x2-5,2-3x3-4", "1-3x2-5,4-9x2-5", "0-1x0-1,1-2x1-2"];
+        let contained = Predicate::AnyContainment;
+        assert_eq!(count_as(areas.iter(), &contained, Numeric::U8, 2), Ok(1));
+        assert_eq!(count_as(areas.iter(), &overlap, Numeric::U128, 2), Ok(2));
+        let volumes = ["0-9x0-9x0-9,5-14x5-14x5-14"];
+        let small = Predicate::UnionAtMost(1875);
+        assert_eq!(count_as(volumes.iter(), &small, Numeric::U16, 3), Ok(1));
+        assert_eq!(
+            count_as(volumes.iter(), &small, Numeric::U16, 4),
+            Err(Error::InvalidDimensions(4))
+        );
+    }
+}
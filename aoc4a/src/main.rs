@@ -1,56 +1,88 @@
-use std::ops::Range;
+use aoc4::{
+    count_as, reallocate_lines, AssignmentIndex, Coverage, Error, Numeric, Predicate, Scope,
+};
 
-fn parse_range(s: &str) -> Range<i32> {
-    let (l, r) = s.split_once('-').unwrap();
-    Range {
-        start: l.parse().unwrap(),
-        end: r.parse().unwrap(),
-    }
-}
-
-fn parse_ranges(s: &str) -> (Range<i32>, Range<i32>) {
-    let (l, r) = s.split_once(',').unwrap();
-    (parse_range(l), parse_range(r))
-}
+/// The puzzle's question, asked of every team unless `--predicate` picks another.
+const DEFAULT: Predicate = Predicate::AnyContainment;
 
-fn overlapping(l: &Range<i32>, r: &Range<i32>) -> bool {
-    (l.start <= r.start && l.end >= r.end) || (l.start >= r.start && l.end <= r.end)
+fn overlaps<T: AsRef<str>>(
+    lines: impl Iterator<Item = T>,
+    predicate: &Predicate,
+    numeric: Numeric,
+    dims: usize,
+) -> Result<usize, Error> {
+    count_as(lines, predicate, numeric, dims)
 }
 
-fn overlaps<T: AsRef<str>>(lines: impl Iterator<Item = T>) -> usize {
-    lines
-        .map(|l| parse_ranges(l.as_ref()))
-        .filter(|(l, r)| overlapping(l, r))
-        .count()
+/// The argument following `flag`, if given.
+fn value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let i = args.iter().position(|a| a == flag)?;
+    Some(args.get(i + 1).expect("Missing flag value"))
 }
 
 fn main() {
-    let overlaps = overlaps(
-        std::io::stdin()
-            .lines()
-            .map(|l| l.expect("Couldn't read line")),
-    );
+    let lines = std::io::stdin()
+        .lines()
+        .map(|l| l.expect("Couldn't read line"));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--index") {
+        let index = AssignmentIndex::from_lines(lines).expect("Invalid assignment");
+        println!("{index}");
+        return;
+    }
+    if args.iter().any(|a| a == "--coverage" || a == "--diagram") {
+        let coverage = Coverage::from_lines(lines).expect("Invalid assignment");
+        if args.iter().any(|a| a == "--diagram") {
+            coverage.diagram().iter().for_each(|row| println!("{row}"));
+            println!("Depth: {}", coverage.depth_strip());
+        }
+        println!("{coverage}");
+        return;
+    }
+    if args
+        .iter()
+        .any(|a| a == "--reallocate" || a == "--reallocate-file")
+    {
+        let scope = match args.iter().any(|a| a == "--reallocate-file") {
+            true => Scope::File,
+            false => Scope::Pair,
+        };
+        let reallocation = reallocate_lines(lines, scope).expect("Invalid assignment");
+        println!("{reallocation}");
+        return;
+    }
+    let predicate =
+        value(&args, "--predicate").map_or(DEFAULT, |p| p.parse().expect("Invalid predicate"));
+    let numeric = value(&args, "--numeric")
+        .map_or(Numeric::I32, |n| n.parse().expect("Invalid numeric type"));
+    let dims = value(&args, "--dims").map_or(1, |d| d.parse().expect("Invalid dimensions"));
+    let overlaps = overlaps(lines, &predicate, numeric, dims).expect("Invalid assignment");
     println!("Result: {overlaps:?}");
 }
 
 #[cfg(test)]
 mod test {
-    use crate::overlaps;
+    use crate::{overlaps, DEFAULT};
+    use aoc4::Numeric;
 
     #[test]
     fn example() {
-        let input = r#"
-        2-4,6-8
-        2-3,4-5
-        5-7,7-9
-        2-8,3-7
-        6-6,4-6
-        2-6,4-8
-        "#
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| l.len() > 0);
+        let input = || {
+            r#"
+            2-4,6-8
+            2-3,4-5
+            5-7,7-9
+            2-8,3-7
+            6-6,4-6
+            2-6,4-8
+            "#
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+        };
 
-        assert_eq!(overlaps(input), 2);
+        assert_eq!(overlaps(input(), &DEFAULT, Numeric::I32, 1), Ok(2));
+        let other_part = "overlap(l, r)".parse().unwrap();
+        assert_eq!(overlaps(input(), &other_part, Numeric::I32, 1), Ok(4));
     }
 }
@@ -0,0 +1,208 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    process,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use crate::{Command, Crane, Crate, Error, State};
+
+/// The state under a caption, with a lifted pile hanging above stack `over`.
+fn frame(caption: &str, state: &State, lifted: Option<(&[Crate], usize)>) -> String {
+    let pitch = state.width() + 3;
+    let width = (pitch * state.stacks().len()).saturating_sub(1);
+    let mut rows = vec![caption.to_string()];
+    if let Some((pile, over)) = lifted {
+        for c in pile.iter().rev() {
+            let row = format!("{}[{c}]", " ".repeat(pitch * (over - 1)));
+            rows.push(format!("{row:width$}"));
+        }
+        rows.push(" ".repeat(width));
+    }
+    rows.push(state.to_string());
+    rows.join("\n")
+}
+
+/// Every frame of replaying `commands` with `crane`: the start, then for each lift the pile
+/// raised above its stack, carried over the target, and dropped.
+pub fn frames(
+    state: &State,
+    commands: &[Command],
+    crane: &dyn Crane,
+) -> Result<Vec<String>, Error> {
+    let mut state = state.clone();
+    let mut frames = vec![frame("start", &state, None)];
+    for cmd in commands {
+        for step in crane.steps(cmd) {
+            state.stack_mut(step.to)?;
+            let pile = state.take(step.from, step.count)?;
+            let lifted = Some((pile.as_slice(), step.from));
+            frames.push(frame(&format!("{cmd}: lift"), &state, lifted));
+            let carried = Some((pile.as_slice(), step.to));
+            frames.push(frame(&format!("{cmd}: carry"), &state, carried));
+            state.put(step.to, pile)?;
+            frames.push(frame(&format!("{cmd}: drop"), &state, None));
+        }
+    }
+    Ok(frames)
+}
+
+/// The controlling terminal with line buffering and echo off, restored when dropped. Keys are
+/// read from here rather than stdin, which carries the puzzle input.
+struct Keys {
+    tty: File,
+    saved: String,
+}
+
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output = process::Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .output()?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => Err(io::Error::other("stty failed")),
+    }
+}
+
+impl Keys {
+    fn open() -> io::Result<Self> {
+        let tty = File::open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        stty(&tty, &["-icanon", "-echo", "min", "0", "time", "0"])?;
+        Ok(Self { tty, saved })
+    }
+
+    /// The next key pressed, without waiting.
+    fn poll(&mut self) -> Option<u8> {
+        let mut key = [0];
+        match self.tty.read(&mut key) {
+            Ok(1) => Some(key[0]),
+            _ => None,
+        }
+    }
+}
+
+impl Drop for Keys {
+    fn drop(&mut self) {
+        let _ = stty(&self.tty, &[&self.saved]);
+    }
+}
+
+/// Shows the frames in place, `delay` apart. Space pauses, `n` steps while paused, `+` and
+/// `-` change speed and `q` quits.
+pub fn play(frames: &[String], mut delay: Duration) -> io::Result<()> {
+    let mut keys = Keys::open()?;
+    let mut out = io::stdout();
+    let mut paused = false;
+    let mut i = 0;
+    while i < frames.len() {
+        write!(
+            out,
+            "\x1b[2J\x1b[H{}\n\n[space] pause  [n] step  [+/-] speed  [q] quit   {}/{}  {}ms{}\n",
+            frames[i],
+            i + 1,
+            frames.len(),
+            delay.as_millis(),
+            if paused { "  paused" } else { "" }
+        )?;
+        out.flush()?;
+        let shown = Instant::now();
+        loop {
+            match keys.poll() {
+                Some(b'q') => return Ok(()),
+                Some(b' ') => {
+                    paused = !paused;
+                    break;
+                }
+                Some(b'n') if paused => {
+                    i += 1;
+                    break;
+                }
+                Some(b'+') => delay = (delay / 2).max(Duration::from_millis(10)),
+                Some(b'-') => delay *= 2,
+                _ => {}
+            }
+            if !paused && shown.elapsed() >= delay {
+                i += 1;
+                break;
+            }
+            sleep(Duration::from_millis(10));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, frames, Command, Error, State};
+
+    #[test]
+    fn snapshot() {
+        let state = State::new(vec![vec!['A', 'B'], vec!['C']]);
+        let cmd: Command = "move 2 from 1 to 2".parse().unwrap();
+        let frames = frames(&state, &[cmd], crane("9000").unwrap().as_ref()).unwrap();
+        assert_eq!(frames.len(), 7);
+        assert_eq!(frames[0], "start\n[B]    \n[A] [C]\n 1   2 ");
+        assert_eq!(
+            frames[1],
+            "move 2 from 1 to 2: lift\n[B]    \n       \n[A] [C]\n 1   2 "
+        );
+        assert_eq!(
+            frames[2],
+            "move 2 from 1 to 2: carry\n    [B]\n       \n[A] [C]\n 1   2 "
+        );
+        assert_eq!(
+            frames[3],
+            "move 2 from 1 to 2: drop\n    [B]\n[A] [C]\n 1   2 "
+        );
+        assert_eq!(
+            frames[6],
+            "move 2 from 1 to 2: drop\n    [A]\n    [B]\n    [C]\n 1   2 "
+        );
+    }
+
+    #[test]
+    fn missing_stack() {
+        let state = State::new(vec![vec!['A', 'B'], vec!['C']]);
+        for line in [
+            "move 1 from 1 to 0",
+            "move 1 from 1 to 3",
+            "move 1 from 0 to 2",
+        ] {
+            let cmd: Command = line.parse().unwrap();
+            for name in ["9000", "9001"] {
+                let crane = crane(name).unwrap();
+                assert_eq!(
+                    frames(&state, &[cmd], crane.as_ref()),
+                    Err(Error::Underflow),
+                    "{name}: {line}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn steps_match_execute() {
+        let state = State::new(vec![vec!['A', 'B', 'C', 'D', 'E'], vec![], vec!['F']]);
+        let cmd: Command = "move 5 from 1 to 3".parse().unwrap();
+        for name in [
+            "9000",
+            "9001",
+            "batch:2",
+            "adjacent:9000",
+            "adjacent:batch:3",
+        ] {
+            let crane = crane(name).unwrap();
+            let mut expected = state.clone();
+            crane.execute(&mut expected, &cmd).unwrap();
+            let last = frames(&state, &[cmd], crane.as_ref())
+                .unwrap()
+                .pop()
+                .unwrap();
+            let (_, diagram) = last.split_once('\n').unwrap();
+            assert_eq!(diagram, expected.to_string(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Error, Instruction, Stack};
+
+/// `move <count> from <from> to <to>`, with stacks numbered from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub from: usize,
+    pub to: usize,
+    pub count: usize,
+}
+
+impl FromStr for Command {
+    type Err = Error;
+
+    /// Only the plain form, with numbered stacks; see [`Instruction`] for the rest of the
+    /// language.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<Instruction>()? {
+            Instruction::Move {
+                count: Some(count),
+                from: Stack::Number(from),
+                to,
+            } => match to[..] {
+                [Stack::Number(to)] => Ok(Self { from, to, count }),
+                _ => Err(Error::InvalidCommand),
+            },
+            _ => Err(Error::InvalidCommand),
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+    }
+}
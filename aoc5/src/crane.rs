@@ -0,0 +1,371 @@
+use std::num::NonZeroUsize;
+
+use crate::{Command, Error, State};
+
+/// A crane model, deciding the order the crates of one command land in.
+pub trait Crane {
+    fn execute(&self, state: &mut State, cmd: &Command) -> Result<(), Error>;
+
+    /// The lifts the crane makes for one command, each moving a pile without changing its
+    /// order; replaying them gives the same result as [`Crane::execute`].
+    fn steps(&self, cmd: &Command) -> Vec<Command>;
+
+    /// Every stack a command can change, without working out the lifts.
+    fn touched(&self, cmd: &Command) -> Vec<usize> {
+        vec![cmd.from, cmd.to]
+    }
+
+    /// Where the crate `depth` below the top of stack `s` after `cmd` was before it, as a
+    /// stack and depth. Lets a crate be followed backwards without moving any others.
+    fn before(&self, cmd: &Command, s: usize, depth: usize) -> (usize, usize) {
+        self.steps(cmd)
+            .iter()
+            .rev()
+            .fold((s, depth), |(s, depth), step| {
+                trace(step, s, depth, step.count)
+            })
+    }
+}
+
+/// [`Crane::before`] for a crane lifting `lift` crates at a time, each lift keeping its order.
+fn trace(cmd: &Command, s: usize, depth: usize, lift: usize) -> (usize, usize) {
+    let count = cmd.count;
+    if cmd.from == cmd.to || (s != cmd.from && s != cmd.to) {
+        return (s, depth);
+    }
+    if s == cmd.from {
+        return (s, depth + count);
+    }
+    if depth >= count {
+        return (s, depth - count);
+    }
+    // The top lift lands first, so counting up from the bottom of the landed pile every lift
+    // is full except the last, which holds what was left at the bottom of the pile.
+    let lift = lift.clamp(1, count);
+    let full = (count.div_ceil(lift) - 1) * lift;
+    let landed = count - 1 - depth;
+    let pile = match landed < full {
+        true => count - (landed / lift + 1) * lift + landed % lift,
+        false => landed - full,
+    };
+    (cmd.from, count - 1 - pile)
+}
+
+/// Lifts one crate at a time, so a moved pile ends up reversed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrateMover9000;
+
+/// Lifts the whole pile at once, keeping its order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrateMover9001;
+
+/// Lifts at most this many crates at a time, each batch keeping its order.
+#[derive(Debug, Clone, Copy)]
+pub struct Batched(pub NonZeroUsize);
+
+/// Can only reach neighbouring stacks, so a pile is relayed one stack at a time by the
+/// inner crane.
+pub struct Adjacent(pub Box<dyn Crane>);
+
+impl Crane for CrateMover9000 {
+    fn execute(&self, state: &mut State, cmd: &Command) -> Result<(), Error> {
+        state.transfer(cmd.from, cmd.to, cmd.count, 1)
+    }
+
+    fn before(&self, cmd: &Command, s: usize, depth: usize) -> (usize, usize) {
+        trace(cmd, s, depth, 1)
+    }
+
+    fn steps(&self, cmd: &Command) -> Vec<Command> {
+        vec![Command { count: 1, ..*cmd }; cmd.count]
+    }
+}
+
+impl Crane for CrateMover9001 {
+    fn execute(&self, state: &mut State, cmd: &Command) -> Result<(), Error> {
+        state.transfer(cmd.from, cmd.to, cmd.count, cmd.count)
+    }
+
+    fn before(&self, cmd: &Command, s: usize, depth: usize) -> (usize, usize) {
+        trace(cmd, s, depth, cmd.count)
+    }
+
+    fn steps(&self, cmd: &Command) -> Vec<Command> {
+        vec![*cmd]
+    }
+}
+
+impl Crane for Batched {
+    fn execute(&self, state: &mut State, cmd: &Command) -> Result<(), Error> {
+        state.transfer(cmd.from, cmd.to, cmd.count, self.0.get())
+    }
+
+    fn before(&self, cmd: &Command, s: usize, depth: usize) -> (usize, usize) {
+        trace(cmd, s, depth, self.0.get())
+    }
+
+    fn steps(&self, cmd: &Command) -> Vec<Command> {
+        let lift = self.0.get();
+        (0..cmd.count)
+            .step_by(lift)
+            .map(|lifted| Command {
+                count: lift.min(cmd.count - lifted),
+                ..*cmd
+            })
+            .collect()
+    }
+}
+
+impl Adjacent {
+    fn hops(cmd: &Command) -> Vec<Command> {
+        let mut hops = vec![];
+        let mut at = cmd.from;
+        while at != cmd.to {
+            let next = match at < cmd.to {
+                true => at + 1,
+                false => at - 1,
+            };
+            hops.push(Command {
+                from: at,
+                to: next,
+                count: cmd.count,
+            });
+            at = next;
+        }
+        hops
+    }
+}
+
+impl Crane for Adjacent {
+    fn execute(&self, state: &mut State, cmd: &Command) -> Result<(), Error> {
+        for hop in Self::hops(cmd) {
+            self.0.execute(state, &hop)?;
+        }
+        Ok(())
+    }
+
+    fn steps(&self, cmd: &Command) -> Vec<Command> {
+        Self::hops(cmd)
+            .iter()
+            .flat_map(|hop| self.0.steps(hop))
+            .collect()
+    }
+
+    fn touched(&self, cmd: &Command) -> Vec<usize> {
+        match cmd.from <= cmd.to {
+            true => (cmd.from..=cmd.to).collect(),
+            false => (cmd.to..=cmd.from).collect(),
+        }
+    }
+
+    fn before(&self, cmd: &Command, s: usize, depth: usize) -> (usize, usize) {
+        Self::hops(cmd)
+            .iter()
+            .rev()
+            .fold((s, depth), |(s, depth), hop| self.0.before(hop, s, depth))
+    }
+}
+
+/// Picks a crane by name: `9000`, `9001`, `batch:K`, or `adjacent:` followed by another name.
+pub fn crane(name: &str) -> Result<Box<dyn Crane>, Error> {
+    let invalid = || Error::InvalidCrane(name.to_string());
+    match name.split_once(':') {
+        None if name == "9000" => Ok(Box::new(CrateMover9000)),
+        None if name == "9001" => Ok(Box::new(CrateMover9001)),
+        Some(("batch", k)) => Ok(Box::new(Batched(k.parse().map_err(|_| invalid())?))),
+        Some(("adjacent", inner)) => Ok(Box::new(Adjacent(crane(inner)?))),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, random::Random, Command, Error, Instruction, State};
+
+    const EXAMPLE: &str = "    [D]
+[N] [C]
+[Z] [M] [P]
+ 1   2   3
+
+move 1 from 2 to 1
+move 3 from 1 to 3
+move 2 from 2 to 1
+move 1 from 1 to 2";
+
+    fn run(name: &str) -> Result<String, Error> {
+        let crane = crane(name)?;
+        let mut lines = EXAMPLE.lines();
+        let mut state = State::parse(&mut lines)?;
+        for line in lines {
+            crane.execute(&mut state, &line.parse()?)?;
+        }
+        Ok(state.peek())
+    }
+
+    #[test]
+    fn models() {
+        assert_eq!(run("9000"), Ok("CMZ".to_string()));
+        assert_eq!(run("9001"), Ok("MCD".to_string()));
+        assert_eq!(run("batch:1"), Ok("CMZ".to_string()));
+        assert_eq!(run("batch:3"), Ok("MCD".to_string()));
+        assert_eq!(run("batch:2"), Ok("MCZ".to_string()));
+        assert_eq!(run("adjacent:9001"), Ok("MCD".to_string()));
+        assert_eq!(run("adjacent:9000"), Ok("CMD".to_string()));
+        assert_eq!(
+            run("batch:0"),
+            Err(Error::InvalidCrane("batch:0".to_string()))
+        );
+        assert!(run("9002").is_err());
+    }
+
+    #[test]
+    fn underflow() {
+        let mut state = State::new(vec![vec!['A'], vec![]]);
+        let cmd: Command = "move 2 from 1 to 2".parse().unwrap();
+        assert_eq!(
+            crane("9000").unwrap().execute(&mut state, &cmd),
+            Err(Error::Underflow)
+        );
+        assert_eq!(
+            crane("9001").unwrap().execute(&mut state, &cmd),
+            Err(Error::Underflow)
+        );
+    }
+
+    #[test]
+    fn touched() {
+        let cmd: Command = "move 5 from 4 to 1".parse().unwrap();
+        for name in [
+            "9000",
+            "9001",
+            "batch:2",
+            "adjacent:9000",
+            "adjacent:batch:3",
+        ] {
+            let crane = crane(name).unwrap();
+            let touched = crane.touched(&cmd);
+            for step in crane.steps(&cmd) {
+                assert!(
+                    touched.contains(&step.from) && touched.contains(&step.to),
+                    "{name}"
+                );
+            }
+        }
+        assert_eq!(crane("adjacent:9001").unwrap().touched(&cmd), [1, 2, 3, 4]);
+        assert_eq!(crane("batch:3").unwrap().touched(&cmd), [4, 1]);
+    }
+
+    /// The crate-at-a-time cranes these replaced, for comparison.
+    fn one_at_a_time(state: &mut State, cmd: &Command, lift: usize) {
+        let mut remaining = cmd.count;
+        while remaining > 0 {
+            let mut buffer = vec![];
+            for _i in 0..lift.min(remaining) {
+                buffer.push(state.pop_from(cmd.from).unwrap());
+            }
+            for c in buffer.iter().rev() {
+                state.push_to(cmd.to, c.clone()).unwrap();
+            }
+            remaining -= buffer.len();
+        }
+    }
+
+    /// `stacks` stacks of `height` crates each and `moves` valid commands shifting up to
+    /// `spread` crates at once.
+    fn generate(
+        stacks: usize,
+        height: usize,
+        moves: usize,
+        spread: usize,
+    ) -> (State, Vec<Command>) {
+        let mut random = Random::new(5);
+        let crates = (0..stacks)
+            .map(|_| (0..height).map(|_| random.letter()).collect())
+            .collect();
+        let mut heights = vec![height; stacks];
+        let mut commands = vec![];
+        while commands.len() < moves {
+            let (from, to) = (random.below(stacks), random.below(stacks));
+            if heights[from] == 0 {
+                continue;
+            }
+            let count = 1 + random.below(heights[from].min(spread));
+            heights[from] -= count;
+            heights[to] += count;
+            commands.push(Command {
+                from: from + 1,
+                to: to + 1,
+                count,
+            });
+        }
+        (State::new(crates), commands)
+    }
+
+    #[test]
+    fn matches_one_at_a_time() {
+        let (start, commands) = generate(5, 40, 500, 30);
+        for (name, lift) in [("9000", 1), ("9001", usize::MAX), ("batch:3", 3)] {
+            let crane = crane(name).unwrap();
+            let (mut bulk, mut single) = (start.clone(), start.clone());
+            for cmd in &commands {
+                crane.execute(&mut bulk, cmd).unwrap();
+                one_at_a_time(&mut single, cmd, lift);
+                assert_eq!(bulk, single, "{name}: {cmd}");
+            }
+        }
+    }
+
+    /// `cargo test --release -- --ignored --nocapture`
+    ///
+    /// Times the crane alone, the binary's path through [`Instruction::execute`] and the
+    /// REPL's through [`State::apply`], which also saves the touched stacks for undo.
+    #[test]
+    #[ignore]
+    fn bench_bulk() {
+        for height in [1_000, 100_000, 1_000_000] {
+            let (start, commands) = generate(9, height, 1_000, usize::MAX);
+            let instructions: Vec<Instruction> = commands
+                .iter()
+                .map(|cmd| cmd.to_string().parse().unwrap())
+                .collect();
+            for (name, lift) in [("9000", 1), ("9001", usize::MAX)] {
+                let crane = crane(name).unwrap();
+                let mut single = start.clone();
+                let begin = std::time::Instant::now();
+                for cmd in &commands {
+                    one_at_a_time(std::hint::black_box(&mut single), cmd, lift);
+                }
+                let before = begin.elapsed();
+                let mut bulk = start.clone();
+                let begin = std::time::Instant::now();
+                for cmd in &commands {
+                    crane.execute(std::hint::black_box(&mut bulk), cmd).unwrap();
+                }
+                let after = begin.elapsed();
+                assert!(single == bulk);
+                drop(single);
+                let mut binary = start.clone();
+                let begin = std::time::Instant::now();
+                for instruction in &instructions {
+                    let state = std::hint::black_box(&mut binary);
+                    instruction.execute(state, crane.as_ref()).unwrap();
+                }
+                let executed = begin.elapsed();
+                assert!(binary == bulk);
+                drop(binary);
+                let mut repl = start.clone();
+                let begin = std::time::Instant::now();
+                for cmd in &commands {
+                    let state = std::hint::black_box(&mut repl);
+                    std::hint::black_box(state.apply(crane.as_ref(), cmd).unwrap());
+                }
+                let applied = begin.elapsed();
+                assert!(repl == bulk);
+                println!(
+                    "{name}, 9 stacks of {height:>9}: one at a time {before:>10.2?}, bulk {after:>10.2?} ({:.1}x), instructions {executed:>10.2?}, apply {applied:>10.2?}",
+                    before.as_secs_f64() / after.as_secs_f64()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::{cmp::Ordering, fmt::Display, ops::Deref};
+
+/// The most bytes of a name kept inline, as many as fit beside the tag in 16 bytes.
+const INLINE: usize = 14;
+
+/// A crate's name. The puzzle's single letters, and any other short name, are stored inline so
+/// crates can be made, moved and cloned without allocating.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Crate(Name);
+
+/// Inline exactly when it fits, so equal names always have equal representations. Longer
+/// names sit behind a thin pointer to keep a crate two words wide.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Name {
+    Inline { len: u8, bytes: [u8; INLINE] },
+    Long(Box<Box<str>>),
+}
+
+impl Crate {
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Name::Inline { len, bytes } => {
+                std::str::from_utf8(&bytes[..*len as usize]).expect("Inline names are UTF-8")
+            }
+            Name::Long(name) => name,
+        }
+    }
+}
+
+impl Deref for Crate {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Crate {
+    fn from(name: &str) -> Self {
+        if name.len() > INLINE {
+            return Self(Name::Long(Box::new(name.into())));
+        }
+        let mut bytes = [0; INLINE];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Self(Name::Inline {
+            len: name.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl From<String> for Crate {
+    fn from(name: String) -> Self {
+        match name.len() > INLINE {
+            true => Self(Name::Long(Box::new(name.into_boxed_str()))),
+            false => Self::from(name.as_str()),
+        }
+    }
+}
+
+impl From<char> for Crate {
+    fn from(c: char) -> Self {
+        Self::from(c.encode_utf8(&mut [0; 4]) as &str)
+    }
+}
+
+/// Builds a name from its characters, only allocating once it outgrows the inline space.
+impl FromIterator<char> for Crate {
+    fn from_iter<I: IntoIterator<Item = char>>(chars: I) -> Self {
+        let mut bytes = [0; INLINE];
+        let mut len = 0;
+        let mut chars = chars.into_iter();
+        while let Some(c) = chars.next() {
+            if len + c.len_utf8() > INLINE {
+                let mut name = String::from(std::str::from_utf8(&bytes[..len]).unwrap());
+                name.push(c);
+                name.extend(chars);
+                return Self(Name::Long(Box::new(name.into_boxed_str())));
+            }
+            len += c.encode_utf8(&mut bytes[len..]).len();
+        }
+        Self(Name::Inline {
+            len: len as u8,
+            bytes,
+        })
+    }
+}
+
+impl PartialOrd for Crate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Crate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Display for Crate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Crate;
+
+    #[test]
+    fn names() {
+        let long = "A".repeat(30);
+        for name in [
+            "A",
+            "DEF",
+            "ü",
+            "🦀",
+            "ABCDEFGHIJKLMN",
+            "ABCDEFGHIJKLMNO",
+            &long,
+        ] {
+            let from_str = Crate::from(name);
+            assert_eq!(from_str.as_str(), name);
+            assert_eq!(Crate::from(name.to_string()), from_str);
+            assert_eq!(name.chars().collect::<Crate>(), from_str);
+            assert_eq!(from_str.to_string(), name);
+        }
+        assert_eq!(Crate::from('ü'), Crate::from("ü"));
+        let mut sorted = [Crate::from("B"), Crate::from(long.as_str())];
+        sorted.sort();
+        assert_eq!(sorted[0].as_str(), long);
+        assert_eq!(std::mem::size_of::<Crate>(), 16);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::{Crate, Error};
+
+/// Tabs advance to the next multiple of this, the pitch of the puzzle's own diagram.
+const TAB: usize = 4;
+
+fn error(message: String, line: usize, column: usize) -> Error {
+    Error::InvalidDiagram(format!("{message} at line {line}, column {}", column + 1))
+}
+
+fn expand(line: &str) -> Vec<char> {
+    let mut chars = vec![];
+    for c in line.chars() {
+        match c {
+            '\t' => chars.resize((chars.len() / TAB + 1) * TAB, ' '),
+            c => chars.push(c),
+        }
+    }
+    chars
+}
+
+/// Each whitespace separated label with the columns it covers.
+fn labels(line: &[char]) -> Vec<(usize, usize, String)> {
+    let mut labels = vec![];
+    let mut i = 0;
+    while i < line.len() {
+        if line[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < line.len() && !line[i].is_whitespace() {
+            i += 1;
+        }
+        labels.push((start, i, line[start..i].iter().collect()));
+    }
+    labels
+}
+
+/// Each `[crate]` with the columns it covers, brackets included.
+fn crates(line: &[char], n: usize) -> Result<Vec<(usize, usize, Crate)>, Error> {
+    let mut crates = vec![];
+    let mut i = 0;
+    while i < line.len() {
+        match line[i] {
+            c if c.is_whitespace() => i += 1,
+            '[' => {
+                let Some(end) = line[i..].iter().position(|&c| c == ']').map(|e| i + e) else {
+                    return Err(error("unclosed `[`".to_string(), n, i));
+                };
+                let id: Crate = line[i + 1..end].iter().copied().collect();
+                if id.is_empty() || id.contains(|c: char| c.is_whitespace() || c == '[') {
+                    return Err(error(format!("invalid crate `[{id}]`"), n, i));
+                }
+                crates.push((i, end + 1, id));
+                i = end + 1;
+            }
+            c => return Err(error(format!("unexpected `{c}`"), n, i)),
+        }
+    }
+    Ok(crates)
+}
+
+/// Reads the crate diagram up to and including the blank line after it, giving the stacks,
+/// bottom first, and their labels. Each crate belongs to the stack whose label it sits above,
+/// so columns need not line up exactly, labels and crates can be any width, and lines can be
+/// indented with tabs or have their trailing spaces stripped.
+pub(crate) fn parse(
+    lines: &mut impl Iterator<Item = impl Into<String>>,
+) -> Result<(Vec<Vec<Crate>>, Vec<String>), Error> {
+    let mut rows: Vec<Vec<char>> = vec![];
+    for line in lines.by_ref() {
+        let line: String = line.into();
+        if line.trim().is_empty() {
+            break;
+        }
+        rows.push(expand(&line));
+    }
+    let Some(footer) = rows.pop() else {
+        return Ok((vec![], vec![]));
+    };
+    let labels = labels(&footer);
+    for (i, (start, _, label)) in labels.iter().enumerate() {
+        if label.starts_with('[') {
+            let message = "expected stack labels".to_string();
+            return Err(error(message, rows.len() + 1, *start));
+        }
+        if labels[..i].iter().any(|(_, _, l)| l == label) {
+            let message = format!("stack label `{label}` is used twice");
+            return Err(error(message, rows.len() + 1, *start));
+        }
+    }
+    let mut stacks: Vec<Vec<Crate>> = vec![vec![]; labels.len()];
+    for (level, (i, row)) in rows.iter().enumerate().rev().enumerate() {
+        for (start, end, id) in crates(row, i + 1)? {
+            let mut under = labels
+                .iter()
+                .enumerate()
+                .filter(|(_, (s, e, _))| *s < end && start < *e);
+            let s = match (under.next(), under.next()) {
+                (Some((s, _)), None) => s,
+                (None, _) => {
+                    let message = format!("crate `{id}` is not above a stack label");
+                    return Err(error(message, i + 1, start));
+                }
+                (Some(_), Some(_)) => {
+                    let message = format!("crate `{id}` is above more than one stack label");
+                    return Err(error(message, i + 1, start));
+                }
+            };
+            if stacks[s].len() > level {
+                let message = format!("crate `{id}` shares a row of stack `{}`", labels[s].2);
+                return Err(error(message, i + 1, start));
+            }
+            if stacks[s].len() < level {
+                let message = format!("crate `{id}` has nothing under it");
+                return Err(error(message, i + 1, start));
+            }
+            stacks[s].push(id);
+        }
+    }
+    Ok((stacks, labels.into_iter().map(|(_, _, l)| l).collect()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, State};
+
+    const EXAMPLE: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+
+    fn parse(diagram: &str) -> Result<State, Error> {
+        State::parse(&mut diagram.lines())
+    }
+
+    #[test]
+    fn layouts() {
+        let example = parse(EXAMPLE).unwrap();
+        let stripped: Vec<&str> = EXAMPLE.lines().map(|l| l.trim_end()).collect();
+        assert_eq!(parse(&stripped.join("\n")), Ok(example.clone()));
+        let tabbed: Vec<String> = EXAMPLE.lines().map(|l| format!("\t{l}")).collect();
+        assert_eq!(parse(&tabbed.join("\n")), Ok(example.clone()));
+        assert_eq!(
+            parse("\t[D]\n[N]\t[C]\n 1   2"),
+            parse("    [D]\n[N] [C]\n 1   2")
+        );
+        let wide = State::new((1..=12).map(|i| vec![i.to_string()]).collect());
+        assert_eq!(wide.labels()[11], "12");
+        assert!(wide.to_string().ends_with(" 10   11   12 "));
+        assert_eq!(parse(&wide.to_string()), Ok(wide));
+        let named = State::new(vec![vec!["AB"], vec!["C", "DEF"], vec![]]);
+        assert_eq!(
+            named.to_string(),
+            "      [DEF]      \n[AB]  [C]        \n 1     2     3   "
+        );
+        assert_eq!(parse(&named.to_string()), Ok(named.clone()));
+        assert_eq!(named.peek(), "ABDEF ");
+        assert_eq!(parse("[AB] [C]\n 1    2").unwrap().peek(), "ABC");
+    }
+
+    #[test]
+    fn errors() {
+        let message = |diagram| match parse(diagram) {
+            Err(Error::InvalidDiagram(message)) => message,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(
+            message("[A] [B\n 1   2"),
+            "unclosed `[` at line 1, column 5"
+        );
+        assert_eq!(
+            message("[A] x\n 1   2"),
+            "unexpected `x` at line 1, column 5"
+        );
+        assert_eq!(
+            message("[A] [ ]\n 1   2"),
+            "invalid crate `[ ]` at line 1, column 5"
+        );
+        assert_eq!(
+            message("        [A]\n 1   2"),
+            "crate `A` is not above a stack label at line 1, column 9"
+        );
+        assert_eq!(
+            message("[ABCDE]\n 1 2"),
+            "crate `ABCDE` is above more than one stack label at line 1, column 1"
+        );
+        assert_eq!(
+            message("[A][B]\n 1000"),
+            "crate `B` shares a row of stack `1000` at line 1, column 4"
+        );
+        assert_eq!(
+            message("[A]\n    [B]\n 1   2"),
+            "crate `A` has nothing under it at line 1, column 1"
+        );
+        assert_eq!(
+            message("[A] [B]\n 1   1"),
+            "stack label `1` is used twice at line 2, column 6"
+        );
+        assert_eq!(
+            message("[A]\n[B]"),
+            "expected stack labels at line 2, column 1"
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use crate::{Command, Crane, Crate, Error, Instruction, State};
+
+/// The stacks one action touched, each as the height it kept and the crates that were above.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    stacks: Vec<(usize, usize, Vec<Crate>)>,
+}
+
+impl State {
+    /// Saves the top `depth` crates of each touched stack, the most the action can change.
+    fn snapshot(&self, touched: impl Iterator<Item = (usize, usize)>) -> Snapshot {
+        let mut stacks: Vec<(usize, usize, Vec<Crate>)> = vec![];
+        for (s, depth) in touched {
+            let Some(stack) = s.checked_sub(1).and_then(|i| self.stacks().get(i)) else {
+                continue;
+            };
+            if stacks.iter().all(|(saved, _, _)| *saved != s) {
+                let kept = stack.len().saturating_sub(depth);
+                stacks.push((s, kept, stack[kept..].to_vec()));
+            }
+        }
+        Snapshot { stacks }
+    }
+
+    /// Puts the saved stacks back as they were.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        for (s, kept, top) in snapshot.stacks {
+            self.replace(s, kept, top);
+        }
+    }
+
+    /// Runs one command all or nothing: if the crane fails part way every touched stack is put
+    /// back. On success returns what it takes to undo it.
+    pub fn apply(&mut self, crane: &dyn Crane, cmd: &Command) -> Result<Snapshot, Error> {
+        // Cranes only ever take from the top, so nothing below the crates moved off `from`, or
+        // below the old top of any other stack, can change.
+        let touched = crane.touched(cmd).into_iter();
+        let snapshot = self.snapshot(touched.map(|s| match s == cmd.from {
+            true => (s, cmd.count),
+            false => (s, 0),
+        }));
+        match crane.execute(self, cmd) {
+            Ok(()) => Ok(snapshot),
+            Err(error) => {
+                self.restore(snapshot);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Something [`History`] can undo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Command(Command),
+    /// Any instruction other than a plain command.
+    Instruction(Instruction),
+    Restore(String),
+}
+
+/// A state with every change recorded, so commands can be undone and redone and named
+/// checkpoints returned to.
+pub struct History {
+    crane: Box<dyn Crane>,
+    state: State,
+    done: Vec<(Action, Snapshot)>,
+    undone: Vec<Action>,
+    checkpoints: HashMap<String, State>,
+}
+
+impl History {
+    pub fn new(state: State, crane: Box<dyn Crane>) -> Self {
+        Self {
+            crane,
+            state,
+            done: vec![],
+            undone: vec![],
+            checkpoints: HashMap::new(),
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Starts over from another state, forgetting every action and checkpoint.
+    pub fn load(&mut self, state: State) {
+        self.state = state;
+        self.done.clear();
+        self.undone.clear();
+        self.checkpoints.clear();
+    }
+
+    /// Applied actions, oldest first.
+    pub fn log(&self) -> impl Iterator<Item = &Action> {
+        self.done.iter().map(|(action, _)| action)
+    }
+
+    fn perform(&mut self, action: Action) -> Result<(), Error> {
+        let snapshot = match &action {
+            Action::Command(cmd) => self.state.apply(self.crane.as_ref(), cmd)?,
+            Action::Instruction(instruction) => {
+                let touched = instruction.stacks().into_iter();
+                let snapshot = self.state.snapshot(
+                    touched
+                        .filter_map(|s| self.state.number(s).ok())
+                        .map(|s| (s, usize::MAX)),
+                );
+                instruction.execute(&mut self.state, self.crane.as_ref())?;
+                snapshot
+            }
+            Action::Restore(name) => {
+                let saved = self
+                    .checkpoints
+                    .get(name)
+                    .ok_or_else(|| Error::UnknownCheckpoint(name.clone()))?
+                    .clone();
+                // Commands never add or remove stacks, so every checkpoint has the same ones.
+                let snapshot = self
+                    .state
+                    .snapshot((1..=self.state.stacks().len()).map(|s| (s, usize::MAX)));
+                self.state = saved;
+                snapshot
+            }
+        };
+        self.done.push((action, snapshot));
+        Ok(())
+    }
+
+    /// Executes a command atomically, dropping anything that could have been redone.
+    pub fn execute(&mut self, cmd: Command) -> Result<(), Error> {
+        self.perform(Action::Command(cmd))?;
+        self.undone.clear();
+        Ok(())
+    }
+
+    /// Executes any instruction atomically, recording a plain one as its command.
+    pub fn instruct(&mut self, instruction: Instruction) -> Result<(), Error> {
+        match instruction.command(&self.state) {
+            Some(cmd) => self.execute(cmd),
+            None => {
+                self.perform(Action::Instruction(instruction))?;
+                self.undone.clear();
+                Ok(())
+            }
+        }
+    }
+
+    /// Takes back the latest action, if any.
+    pub fn undo(&mut self) -> Option<&Action> {
+        let (action, snapshot) = self.done.pop()?;
+        self.state.restore(snapshot);
+        self.undone.push(action);
+        self.undone.last()
+    }
+
+    /// Applies the latest undone action again, if any.
+    pub fn redo(&mut self) -> Result<Option<&Action>, Error> {
+        let Some(action) = self.undone.pop() else {
+            return Ok(None);
+        };
+        if let Err(error) = self.perform(action.clone()) {
+            self.undone.push(action);
+            return Err(error);
+        }
+        Ok(self.done.last().map(|(action, _)| action))
+    }
+
+    /// Remembers the current state under `name`, replacing any earlier checkpoint of that name.
+    pub fn checkpoint(&mut self, name: &str) {
+        self.checkpoints
+            .insert(name.to_string(), self.state.clone());
+    }
+
+    /// Returns to a checkpoint; this can itself be undone.
+    pub fn restore(&mut self, name: &str) -> Result<(), Error> {
+        self.perform(Action::Restore(name.to_string()))?;
+        self.undone.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, Action, Command, Error, History, State};
+
+    fn cmd(s: &str) -> Command {
+        s.parse().unwrap()
+    }
+
+    fn example() -> History {
+        let stacks = vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']];
+        History::new(State::new(stacks), crane("9000").unwrap())
+    }
+
+    #[test]
+    fn atomic() {
+        let mut state = State::new(vec![vec!['A', 'B'], vec!['C']]);
+        let before = state.clone();
+        let crane = crane("9000").unwrap();
+        let failing = cmd("move 3 from 1 to 2");
+        assert_eq!(
+            crane.execute(&mut state.clone(), &failing),
+            Err(Error::Underflow)
+        );
+        assert_eq!(state.apply(crane.as_ref(), &failing), Err(Error::Underflow));
+        assert_eq!(state, before);
+        assert!(state
+            .apply(crane.as_ref(), &cmd("move 1 from 1 to 4"))
+            .is_err());
+        assert_eq!(state, before);
+        let undo = state
+            .apply(crane.as_ref(), &cmd("move 2 from 1 to 2"))
+            .unwrap();
+        assert_eq!(state.peek(), " A");
+        state.restore(undo);
+        assert_eq!(state, before);
+    }
+
+    #[test]
+    fn undo_every_crane() {
+        let start = State::new(vec![vec!['A', 'B', 'C'], vec!['D'], vec![], vec!['E', 'F']]);
+        for name in [
+            "9000",
+            "9001",
+            "batch:2",
+            "adjacent:9000",
+            "adjacent:batch:2",
+        ] {
+            let crane = crane(name).unwrap();
+            for line in [
+                "move 2 from 1 to 4",
+                "move 3 from 1 to 1",
+                "move 2 from 4 to 1",
+                "move 1 from 2 to 3",
+            ] {
+                let mut state = start.clone();
+                let undo = state.apply(crane.as_ref(), &cmd(line)).unwrap();
+                state.restore(undo);
+                assert_eq!(state, start, "{name}: {line}");
+            }
+        }
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = example();
+        history.execute(cmd("move 1 from 2 to 1")).unwrap();
+        history.execute(cmd("move 3 from 1 to 3")).unwrap();
+        assert_eq!(history.state().peek(), " CZ");
+        assert_eq!(
+            history.execute(cmd("move 9 from 2 to 1")),
+            Err(Error::Underflow)
+        );
+        assert_eq!(history.state().peek(), " CZ");
+        assert_eq!(
+            history.undo(),
+            Some(&Action::Command(cmd("move 3 from 1 to 3")))
+        );
+        assert_eq!(history.state().peek(), "DCP");
+        history.undo();
+        assert_eq!(history.state().peek(), "NDP");
+        assert_eq!(history.undo(), None);
+        history.redo().unwrap();
+        assert_eq!(history.state().peek(), "DCP");
+        assert_eq!(history.log().count(), 1);
+        history.execute(cmd("move 1 from 3 to 2")).unwrap();
+        assert_eq!(history.redo(), Ok(None));
+    }
+
+    #[test]
+    fn checkpoints() {
+        let mut history = example();
+        history.checkpoint("start");
+        history.execute(cmd("move 1 from 2 to 1")).unwrap();
+        history.checkpoint("one");
+        history.execute(cmd("move 3 from 1 to 3")).unwrap();
+        history.restore("start").unwrap();
+        assert_eq!(history.state().peek(), "NDP");
+        history.restore("one").unwrap();
+        assert_eq!(history.state().peek(), "DCP");
+        history.undo();
+        assert_eq!(history.state().peek(), "NDP");
+        history.undo();
+        assert_eq!(history.state().peek(), " CZ");
+        assert_eq!(
+            history.restore("missing"),
+            Err(Error::UnknownCheckpoint("missing".to_string()))
+        );
+        assert_eq!(history.log().count(), 2);
+    }
+}
@@ -0,0 +1,383 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{Command, Crane, Error, State};
+
+/// A stack as an instruction refers to it: by number from 1, or by the label under it in the
+/// diagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stack {
+    Number(usize),
+    Name(String),
+}
+
+/// One line of the command language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    /// `move N from A to B`, or `move all ...`; with several targets, `to B,C`, the crates are
+    /// dealt out one at a time in turn.
+    Move {
+        count: Option<usize>,
+        from: Stack,
+        to: Vec<Stack>,
+    },
+    /// `swap A B` exchanges two whole stacks.
+    Swap(Stack, Stack),
+    /// `reverse A` turns a stack upside down.
+    Reverse(Stack),
+    /// `rotate A K` moves the top crate to the bottom `K` times, or the bottom to the top for
+    /// negative `K`.
+    Rotate(Stack, i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Number(i64),
+    Comma,
+}
+
+/// The tokens of one line, with anything after a `#` dropped.
+struct Tokens {
+    tokens: Vec<Token>,
+    at: usize,
+}
+
+impl Tokens {
+    fn new(line: &str) -> Result<Self, Error> {
+        let line = line.split_once('#').map_or(line, |(code, _)| code);
+        let mut tokens = vec![];
+        let mut chars = line.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == ',' {
+                chars.next();
+                tokens.push(Token::Comma);
+            } else if c.is_alphanumeric() || c == '_' || c == '-' {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_' || c == '-') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(match word.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => Token::Word(word),
+                });
+            } else {
+                return Err(Error::InvalidCommand);
+            }
+        }
+        Ok(Self { tokens, at: 0 })
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.at).cloned();
+        self.at += 1;
+        token
+    }
+
+    /// Takes the next token if it is `word`, ignoring case.
+    fn accept(&mut self, word: &str) -> bool {
+        match self.tokens.get(self.at) {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(word) => {
+                self.at += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn keyword(&mut self, word: &str) -> Result<(), Error> {
+        match self.accept(word) {
+            true => Ok(()),
+            false => Err(Error::InvalidCommand),
+        }
+    }
+
+    fn number(&mut self) -> Result<i64, Error> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(n),
+            _ => Err(Error::InvalidCommand),
+        }
+    }
+
+    fn count(&mut self) -> Result<usize, Error> {
+        usize::try_from(self.number()?).map_err(|_| Error::InvalidCommand)
+    }
+
+    fn stack(&mut self) -> Result<Stack, Error> {
+        match self.next() {
+            Some(Token::Number(n)) => usize::try_from(n)
+                .map(Stack::Number)
+                .map_err(|_| Error::InvalidCommand),
+            Some(Token::Word(name)) => Ok(Stack::Name(name)),
+            _ => Err(Error::InvalidCommand),
+        }
+    }
+
+    fn end(&self) -> Result<(), Error> {
+        match self.at >= self.tokens.len() {
+            true => Ok(()),
+            false => Err(Error::InvalidCommand),
+        }
+    }
+}
+
+impl Instruction {
+    /// Parses one line, giving `None` for one that is blank or only a comment.
+    pub fn parse_line(line: &str) -> Result<Option<Self>, Error> {
+        let mut tokens = Tokens::new(line)?;
+        if tokens.tokens.is_empty() {
+            return Ok(None);
+        }
+        let instruction = if tokens.accept("move") {
+            let count = match tokens.accept("all") {
+                true => None,
+                false => Some(tokens.count()?),
+            };
+            tokens.keyword("from")?;
+            let from = tokens.stack()?;
+            tokens.keyword("to")?;
+            let mut to = vec![tokens.stack()?];
+            while tokens.tokens.get(tokens.at) == Some(&Token::Comma) {
+                tokens.at += 1;
+                to.push(tokens.stack()?);
+            }
+            Instruction::Move { count, from, to }
+        } else if tokens.accept("swap") {
+            Instruction::Swap(tokens.stack()?, tokens.stack()?)
+        } else if tokens.accept("reverse") {
+            Instruction::Reverse(tokens.stack()?)
+        } else if tokens.accept("rotate") {
+            Instruction::Rotate(tokens.stack()?, tokens.number()?)
+        } else {
+            return Err(Error::InvalidCommand);
+        };
+        tokens.end()?;
+        Ok(Some(instruction))
+    }
+
+    /// The plain command this is, if it moves a fixed number of crates between two stacks.
+    pub fn command(&self, state: &State) -> Option<Command> {
+        match self {
+            Instruction::Move {
+                count: Some(count),
+                from,
+                to,
+            } if to.len() == 1 => Some(Command {
+                from: state.number(from).ok()?,
+                to: state.number(&to[0]).ok()?,
+                count: *count,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Every stack the instruction names.
+    pub(crate) fn stacks(&self) -> Vec<&Stack> {
+        match self {
+            Instruction::Move { from, to, .. } => std::iter::once(from).chain(to).collect(),
+            Instruction::Swap(a, b) => vec![a, b],
+            Instruction::Reverse(s) | Instruction::Rotate(s, _) => vec![s],
+        }
+    }
+
+    /// Runs the instruction all or nothing, using `crane` for moves.
+    pub fn execute(&self, state: &mut State, crane: &dyn Crane) -> Result<(), Error> {
+        match self {
+            Instruction::Move { count, from, to } => {
+                let from = state.number(from)?;
+                let to = to
+                    .iter()
+                    .map(|s| state.number(s))
+                    .collect::<Result<Vec<usize>, Error>>()?;
+                let count = match count {
+                    Some(count) => *count,
+                    None => state.stack_mut(from)?.len(),
+                };
+                if state.stack_mut(from)?.len() < count {
+                    return Err(Error::Underflow);
+                }
+                for &s in &to {
+                    state.stack_mut(s)?;
+                }
+                // With the stacks and height checked the crane can't fail part way.
+                if let [to] = to[..] {
+                    return crane.execute(state, &Command { from, to, count });
+                }
+                // Dealt one crate at a time, so the crane makes no difference. A turn onto the
+                // stack being dealt from leaves its top crate there for the next turn.
+                let turns: Vec<usize> = to
+                    .iter()
+                    .cycle()
+                    .take(count)
+                    .filter(|&&s| s != from)
+                    .copied()
+                    .collect();
+                let pile = state.take(from, turns.len())?;
+                for (s, c) in turns.into_iter().zip(pile.into_iter().rev()) {
+                    state.stack_mut(s)?.push(c);
+                }
+                Ok(())
+            }
+            Instruction::Swap(a, b) => {
+                let (a, b) = (state.number(a)?, state.number(b)?);
+                state.swap(a, b)
+            }
+            Instruction::Reverse(s) => {
+                let s = state.number(s)?;
+                state.stack_mut(s)?.reverse();
+                Ok(())
+            }
+            Instruction::Rotate(s, k) => {
+                let s = state.number(s)?;
+                let stack = state.stack_mut(s)?;
+                if !stack.is_empty() {
+                    let k = k.rem_euclid(stack.len() as i64) as usize;
+                    stack.rotate_right(k);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_line(s)?.ok_or(Error::InvalidCommand)
+    }
+}
+
+impl Display for Stack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stack::Number(s) => write!(f, "{s}"),
+            Stack::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Move { count, from, to } => {
+                let to: Vec<String> = to.iter().map(|s| s.to_string()).collect();
+                match count {
+                    Some(count) => write!(f, "move {count} from {from} to {}", to.join(",")),
+                    None => write!(f, "move all from {from} to {}", to.join(",")),
+                }
+            }
+            Instruction::Swap(a, b) => write!(f, "swap {a} {b}"),
+            Instruction::Reverse(s) => write!(f, "reverse {s}"),
+            Instruction::Rotate(s, k) => write!(f, "rotate {s} {k}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, Command, Error, Instruction, Stack, State};
+
+    fn example() -> State {
+        State::parse(&mut "    [D]    \n[N] [C]    \n[Z] [M] [P]\n a   b   c ".lines()).unwrap()
+    }
+
+    fn stacks(state: &State) -> Vec<String> {
+        state
+            .stacks()
+            .iter()
+            .map(|s| s.iter().map(|c| c.as_str()).collect())
+            .collect()
+    }
+
+    fn run(state: &mut State, line: &str) -> Result<(), Error> {
+        let instruction: Instruction = line.parse()?;
+        instruction.execute(state, crane("9000").unwrap().as_ref())
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Instruction::parse_line("  # nothing here"), Ok(None));
+        assert_eq!(Instruction::parse_line(""), Ok(None));
+        assert_eq!(
+            Instruction::parse_line("MOVE 2 from a to 3, c # deal"),
+            Ok(Some(Instruction::Move {
+                count: Some(2),
+                from: Stack::Name("a".to_string()),
+                to: vec![Stack::Number(3), Stack::Name("c".to_string())],
+            }))
+        );
+        for line in [
+            "move all from 1 to 2",
+            "swap 1 b",
+            "reverse 2",
+            "rotate c -1",
+            "move 3 from 1 to 2,3",
+        ] {
+            assert_eq!(line.parse::<Instruction>().unwrap().to_string(), line);
+        }
+        for line in [
+            "move -1 from 1 to 2",
+            "move 1 from 1 to",
+            "move 1 from 1 to 2,",
+            "swap 1",
+            "rotate 1 x",
+            "reverse 1 2",
+            "move 1 from 1 to 2; move",
+            "# only",
+        ] {
+            assert_eq!(
+                line.parse::<Instruction>(),
+                Err(Error::InvalidCommand),
+                "{line}"
+            );
+        }
+        let cmd: Command = "move 1 from 2 to 1".parse().unwrap();
+        assert_eq!(
+            "move 1 from b to a"
+                .parse::<Instruction>()
+                .unwrap()
+                .command(&example()),
+            Some(cmd)
+        );
+        assert!("move 1 from 2 to a".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn execute() {
+        let mut state = example();
+        run(&mut state, "move all from b to a").unwrap();
+        assert_eq!(stacks(&state), ["ZNDCM", "", "P"]);
+        run(&mut state, "move 3 from a to b,c").unwrap();
+        assert_eq!(stacks(&state), ["ZN", "MD", "PC"]);
+        run(&mut state, "swap 1 c").unwrap();
+        run(&mut state, "reverse b").unwrap();
+        assert_eq!(state.peek(), "CMN");
+        run(&mut state, "rotate a 3").unwrap();
+        assert_eq!(stacks(&state)[0], "CP");
+        run(&mut state, "rotate a -1").unwrap();
+        assert_eq!(stacks(&state)[0], "PC");
+        let mut dealt = example();
+        run(&mut dealt, "move 3 from b to b,a").unwrap();
+        assert_eq!(stacks(&dealt), ["ZND", "MC", "P"]);
+        let before = state.clone();
+        assert_eq!(
+            run(&mut state, "move 3 from a to b,c"),
+            Err(Error::Underflow)
+        );
+        assert_eq!(
+            run(&mut state, "move 1 from a to b,4"),
+            Err(Error::Underflow)
+        );
+        assert_eq!(
+            run(&mut state, "swap a d"),
+            Err(Error::UnknownStack("d".to_string()))
+        );
+        assert_eq!(state, before);
+    }
+}
@@ -0,0 +1,38 @@
+mod animation;
+mod command;
+mod crane;
+mod crates;
+mod diagram;
+mod history;
+mod instruction;
+mod planner;
+#[cfg(test)]
+mod random;
+mod repl;
+mod state;
+mod tops;
+mod validate;
+
+pub use animation::{frames, play};
+pub use command::Command;
+pub use crane::{crane, Adjacent, Batched, Crane, CrateMover9000, CrateMover9001};
+pub use crates::Crate;
+pub use history::{Action, History, Snapshot};
+pub use instruction::{Instruction, Stack};
+pub use planner::{plan, Target};
+pub use repl::{run, Session};
+pub use state::State;
+pub use tops::final_tops;
+pub use validate::{validate, Issue, Problem};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Underflow,
+    InvalidCommand,
+    UnknownStack(String),
+    InvalidCrane(String),
+    InvalidDiagram(String),
+    UnknownCheckpoint(String),
+    Unreachable,
+    SearchLimit,
+}
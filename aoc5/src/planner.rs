@@ -0,0 +1,169 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{Command, Crane, Crate, Error, State};
+
+/// What a plan should end in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The top crate of every stack as [`State::peek`] shows it, a space for an empty stack.
+    Tops(String),
+    /// Every stack exactly, whatever the labels.
+    Diagram(State),
+}
+
+impl Target {
+    fn reached(&self, state: &State) -> bool {
+        match self {
+            Target::Tops(tops) => state.peek() == *tops,
+            Target::Diagram(target) => state.stacks() == target.stacks(),
+        }
+    }
+
+    /// Rules out targets no sequence of moves can reach, since moves only rearrange crates.
+    /// Tops can only be split back into crates when every name is a single character, so
+    /// otherwise they are left to the search.
+    fn possible(&self, start: &State) -> bool {
+        let mut crates: Vec<Crate> = start.stacks().concat();
+        crates.sort_unstable();
+        match self {
+            Target::Tops(tops) => {
+                let Some(mut crates) = crates
+                    .iter()
+                    .map(|c| c.parse().ok())
+                    .collect::<Option<Vec<char>>>()
+                else {
+                    return true;
+                };
+                tops.chars().count() == start.stacks().len()
+                    && tops
+                        .chars()
+                        .filter(|&c| c != ' ')
+                        .all(|c| match crates.binary_search(&c) {
+                            Ok(i) => {
+                                crates.remove(i);
+                                true
+                            }
+                            Err(_) => false,
+                        })
+            }
+            Target::Diagram(target) => {
+                let mut wanted: Vec<Crate> = target.stacks().concat();
+                wanted.sort_unstable();
+                target.stacks().len() == start.stacks().len() && wanted == crates
+            }
+        }
+    }
+}
+
+/// The shortest list of commands taking `start` to `target` with `crane`, found by breadth
+/// first search over every `move N from A to B`. Gives up with [`Error::SearchLimit`] after
+/// seeing `limit` distinct states.
+pub fn plan(
+    start: &State,
+    target: &Target,
+    crane: &dyn Crane,
+    limit: usize,
+) -> Result<Vec<Command>, Error> {
+    if !target.possible(start) {
+        return Err(Error::Unreachable);
+    }
+    // Every state seen, with the state and command it was first reached from.
+    let mut seen: Vec<(State, Option<(usize, Command)>)> = vec![(start.clone(), None)];
+    let mut index: HashMap<State, usize> = HashMap::from([(start.clone(), 0)]);
+    let mut queue = VecDeque::from([0]);
+    let stacks = start.stacks().len();
+    while let Some(at) = queue.pop_front() {
+        if target.reached(&seen[at].0) {
+            let mut commands = vec![];
+            let mut at = at;
+            while let Some((parent, cmd)) = seen[at].1 {
+                commands.push(cmd);
+                at = parent;
+            }
+            commands.reverse();
+            return Ok(commands);
+        }
+        for from in 1..=stacks {
+            for to in (1..=stacks).filter(|&to| to != from) {
+                for count in 1..=seen[at].0.stacks()[from - 1].len() {
+                    let cmd = Command { from, to, count };
+                    let mut next = seen[at].0.clone();
+                    if next.apply(crane, &cmd).is_err() || index.contains_key(&next) {
+                        continue;
+                    }
+                    if seen.len() >= limit {
+                        return Err(Error::SearchLimit);
+                    }
+                    index.insert(next.clone(), seen.len());
+                    queue.push_back(seen.len());
+                    seen.push((next, Some((at, cmd))));
+                }
+            }
+        }
+    }
+    Err(Error::Unreachable)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, plan, Error, State, Target};
+
+    fn example() -> State {
+        State::new(vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']])
+    }
+
+    fn run(state: &State, name: &str, target: &Target) -> State {
+        let crane = crane(name).unwrap();
+        let commands = plan(state, target, crane.as_ref(), 100_000).unwrap();
+        let mut state = state.clone();
+        for cmd in &commands {
+            crane.execute(&mut state, cmd).unwrap();
+        }
+        state
+    }
+
+    #[test]
+    fn tops() {
+        let target = Target::Tops("CMZ".to_string());
+        assert_eq!(run(&example(), "9000", &target).peek(), "CMZ");
+        let crane = crane("9001").unwrap();
+        let commands = plan(&example(), &target, crane.as_ref(), 100_000).unwrap();
+        assert_eq!(commands.len(), 4);
+        let target = Target::Tops("DNP".to_string());
+        let commands = plan(&example(), &target, crane.as_ref(), 100_000).unwrap();
+        assert_eq!(
+            commands.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            vec![
+                "move 1 from 1 to 2",
+                "move 2 from 2 to 1",
+                "move 1 from 1 to 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn diagram() {
+        let target = State::new(vec![vec!['C'], vec!['M'], vec!['P', 'D', 'N', 'Z']]);
+        let target = Target::Diagram(target);
+        for name in ["9000", "9001", "adjacent:9000"] {
+            assert_eq!(Target::Diagram(run(&example(), name, &target)), target);
+        }
+    }
+
+    #[test]
+    fn impossible() {
+        let crane = crane("9000").unwrap();
+        for tops in ["XYZ", "DD ", "CM"] {
+            let target = Target::Tops(tops.to_string());
+            assert_eq!(
+                plan(&example(), &target, crane.as_ref(), 100_000),
+                Err(Error::Unreachable)
+            );
+        }
+        let target = Target::Tops("ZNP".to_string());
+        assert_eq!(
+            plan(&example(), &target, crane.as_ref(), 10),
+            Err(Error::SearchLimit)
+        );
+    }
+}
@@ -0,0 +1,22 @@
+/// A small deterministic generator for the randomised tests.
+pub(crate) struct Random(u64);
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// A number below `n`.
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+
+    /// A crate from `A` to `Z`.
+    pub(crate) fn letter(&mut self) -> char {
+        (b'A' + self.below(26) as u8) as char
+    }
+}
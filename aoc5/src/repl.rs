@@ -0,0 +1,188 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{Action, Crane, History, Instruction, State};
+
+const HELP: &str = "move N from A to B  move crates, `all` for the whole stack, `to B,C` to deal
+swap A B            exchange two stacks
+reverse A           turn a stack upside down
+rotate A K          move the top crate to the bottom K times
+# ...               a comment, ignored
+show                show the crate diagram
+peek                show the top crates
+undo, redo          step back or forward through the commands run
+reset               return to the loaded diagram
+save FILE           write the diagram to a file
+load FILE           read a diagram from a file
+quit                leave";
+
+/// An interactive session over one loaded diagram.
+pub struct Session {
+    history: History,
+}
+
+fn describe(action: &Action) -> String {
+    match action {
+        Action::Command(cmd) => cmd.to_string(),
+        Action::Instruction(instruction) => instruction.to_string(),
+        Action::Restore(_) => "reset".to_string(),
+    }
+}
+
+impl Session {
+    pub fn new(state: State, crane: Box<dyn Crane>) -> Self {
+        let mut history = History::new(state, crane);
+        history.checkpoint("start");
+        Self { history }
+    }
+
+    pub fn state(&self) -> &State {
+        self.history.state()
+    }
+
+    fn save(&self, file: &str) -> String {
+        match std::fs::write(file, format!("{}\n", self.state())) {
+            Ok(()) => format!("saved {file}"),
+            Err(error) => format!("error: {error}"),
+        }
+    }
+
+    fn load(&mut self, file: &str) -> String {
+        match std::fs::read_to_string(file) {
+            Ok(diagram) => match State::parse(&mut diagram.lines()) {
+                Ok(state) => {
+                    self.history.load(state);
+                    self.history.checkpoint("start");
+                    self.state().to_string()
+                }
+                Err(error) => format!("error: {error:?}"),
+            },
+            Err(error) => format!("error: {error}"),
+        }
+    }
+
+    /// Runs one input line and returns what to print, or `None` to quit.
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        Some(match word {
+            "" => String::new(),
+            "quit" | "exit" => return None,
+            "help" => HELP.to_string(),
+            "show" => self.state().to_string(),
+            "peek" => self.state().peek(),
+            "undo" => match self.history.undo() {
+                Some(action) => format!("undid {}", describe(action)),
+                None => "nothing to undo".to_string(),
+            },
+            "redo" => match self.history.redo() {
+                Ok(Some(action)) => format!("redid {}", describe(action)),
+                Ok(None) => "nothing to redo".to_string(),
+                Err(error) => format!("error: {error:?}"),
+            },
+            "reset" => match self.history.restore("start") {
+                Ok(()) => self.state().to_string(),
+                Err(error) => format!("error: {error:?}"),
+            },
+            "save" if !rest.is_empty() => self.save(rest),
+            "load" if !rest.is_empty() => self.load(rest),
+            "save" | "load" => format!("usage: {word} FILE"),
+            "move" | "swap" | "reverse" | "rotate" => match Instruction::parse_line(line) {
+                Ok(Some(instruction)) => match self.history.instruct(instruction) {
+                    Ok(()) => self.state().peek(),
+                    Err(error) => format!("error: {error:?}"),
+                },
+                _ => match word {
+                    "move" => "usage: move N from A to B",
+                    "swap" => "usage: swap A B",
+                    "reverse" => "usage: reverse A",
+                    _ => "usage: rotate A K",
+                }
+                .to_string(),
+            },
+            _ if word.starts_with('#') => String::new(),
+            _ => format!("unknown command `{word}`, try `help`"),
+        })
+    }
+}
+
+/// Prompts for and runs lines from `input` until it ends or the user quits.
+pub fn run(session: &mut Session, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let Some(reply) = session.handle(&line?) else {
+            break;
+        };
+        if !reply.is_empty() {
+            writeln!(output, "{reply}")?;
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, run, Session, State};
+
+    #[test]
+    fn session() {
+        let state = State::new(vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]);
+        let mut session = Session::new(state, crane("9000").unwrap());
+        let file = std::env::temp_dir().join(format!("aoc5-repl-{}.txt", std::process::id()));
+        let file = file.to_str().unwrap();
+        let script = format!(
+            "peek\nmove 1 from 2 to 1\nmove 9 from 1 to 3\nmove x\nsave {file}\nundo\nundo\n\
+             redo\nreset\nload {file}\nshow\nfly\nquit\npeek\n"
+        );
+        let mut output = vec![];
+        run(&mut session, script.as_bytes(), &mut output).unwrap();
+        std::fs::remove_file(file).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let diagram = "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+        let expected = [
+            "> NDP",
+            "> DCP",
+            "> error: Underflow",
+            "> usage: move N from A to B",
+            &format!("> saved {file}"),
+            "> undid move 1 from 2 to 1",
+            "> nothing to undo",
+            "> redid move 1 from 2 to 1",
+            ">     [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ",
+            &format!("> {diagram}"),
+            &format!("> {diagram}"),
+            "> unknown command `fly`, try `help`",
+            "> ",
+        ];
+        assert_eq!(output, expected.join("\n"));
+        assert_eq!(session.state().peek(), "DCP");
+    }
+
+    #[test]
+    fn instructions() {
+        let diagram = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n a   b   c ";
+        let state = State::parse(&mut diagram.lines()).unwrap();
+        let mut session = Session::new(state, crane("9000").unwrap());
+        let script = "# named stacks\nmove all from b to c # clear b\nswap a b\nreverse c\n\
+                      rotate c 1\nundo\nmove 1 from c to a,b\nmove 1 from x to a\nswap a\nundo\n";
+        let mut output = vec![];
+        run(&mut session, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let expected = [
+            "> > N M",
+            ">  NM",
+            ">  NP",
+            ">  ND",
+            "> undid rotate c 1",
+            "> PND",
+            "> error: UnknownStack(\"x\")",
+            "> usage: swap A B",
+            "> undid move 1 from c to a,b",
+            "> ",
+        ];
+        assert_eq!(output, expected.join("\n"));
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+use crate::{diagram, Crate, Error, Stack};
+
+/// Every stack of crates, bottom first, with the label under each one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct State {
+    stacks: Vec<Vec<Crate>>,
+    labels: Vec<String>,
+}
+
+impl State {
+    /// Stacks labelled with their numbers.
+    pub fn new<C: Into<Crate>>(stacks: Vec<Vec<C>>) -> Self {
+        let labels = (1..=stacks.len()).map(|i| i.to_string()).collect();
+        let stacks = stacks
+            .into_iter()
+            .map(|stack| stack.into_iter().map(Into::into).collect())
+            .collect();
+        Self { stacks, labels }
+    }
+
+    /// Reads the crate diagram up to and including the blank line after it.
+    pub fn parse(lines: &mut impl Iterator<Item = impl Into<String>>) -> Result<Self, Error> {
+        let (stacks, labels) = diagram::parse(lines)?;
+        Ok(Self { stacks, labels })
+    }
+
+    pub fn stacks(&self) -> &[Vec<Crate>] {
+        &self.stacks
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// The number of the stack a command refers to, looking names up among the labels.
+    pub fn number(&self, stack: &Stack) -> Result<usize, Error> {
+        match stack {
+            Stack::Number(s) => Ok(*s),
+            Stack::Name(name) => match self.labels.iter().position(|l| l == name) {
+                Some(i) => Ok(i + 1),
+                None => Err(Error::UnknownStack(name.clone())),
+            },
+        }
+    }
+
+    pub(crate) fn stack_mut(&mut self, s: usize) -> Result<&mut Vec<Crate>, Error> {
+        s.checked_sub(1)
+            .and_then(|s| self.stacks.get_mut(s))
+            .ok_or(Error::Underflow)
+    }
+
+    pub(crate) fn swap(&mut self, a: usize, b: usize) -> Result<(), Error> {
+        self.stack_mut(a)?;
+        self.stack_mut(b)?;
+        self.stacks.swap(a - 1, b - 1);
+        Ok(())
+    }
+
+    /// The top crate of every stack, a space for empty ones.
+    pub fn peek(&self) -> String {
+        let mut result = String::with_capacity(self.stacks.len());
+        for stack in &self.stacks {
+            match stack.last() {
+                Some(c) => result.push_str(c),
+                None => result.push(' '),
+            }
+        }
+        result
+    }
+
+    /// How wide a crate's name can be in the diagram, also leaving room for the labels.
+    pub(crate) fn width(&self) -> usize {
+        let crates = self.stacks.iter().flatten().map(|c| c.chars().count());
+        let labels = self
+            .labels
+            .iter()
+            .map(|l| l.chars().count().saturating_sub(1));
+        crates.chain(labels).max().unwrap_or(0).max(1)
+    }
+
+    /// Stacks are numbered from 1.
+    pub fn pop_from(&mut self, s: usize) -> Option<Crate> {
+        self.stacks.get_mut(s.checked_sub(1)?)?.pop()
+    }
+
+    pub fn push_to(&mut self, s: usize, c: Crate) -> Result<(), Error> {
+        match s.checked_sub(1).and_then(|s| self.stacks.get_mut(s)) {
+            Some(stack) => {
+                stack.push(c);
+                Ok(())
+            }
+            None => Err(Error::Underflow),
+        }
+    }
+
+    /// Removes the top `count` crates of stack `s`, bottom first, or nothing if there are
+    /// fewer.
+    pub fn take(&mut self, s: usize, count: usize) -> Result<Vec<Crate>, Error> {
+        let stack = self.stack_mut(s)?;
+        if stack.len() < count {
+            return Err(Error::Underflow);
+        }
+        Ok(stack.split_off(stack.len() - count))
+    }
+
+    /// Cuts stack `s` down to `kept` crates and puts `top` back on it.
+    pub(crate) fn replace(&mut self, s: usize, kept: usize, top: Vec<Crate>) {
+        if let Some(stack) = s.checked_sub(1).and_then(|s| self.stacks.get_mut(s)) {
+            stack.truncate(kept);
+            stack.extend(top);
+        }
+    }
+
+    /// Puts a pile on stack `s`, keeping its order.
+    pub fn put(&mut self, s: usize, pile: Vec<Crate>) -> Result<(), Error> {
+        self.stack_mut(s)?.extend(pile);
+        Ok(())
+    }
+
+    /// Moves the top `count` crates of stack `from` onto `to`, lifting `lift` at a time with
+    /// each lift keeping its order. Nothing moves unless both stacks exist and `from` has
+    /// enough crates; moving a stack onto itself leaves it as it was.
+    pub fn transfer(
+        &mut self,
+        from: usize,
+        to: usize,
+        count: usize,
+        lift: usize,
+    ) -> Result<(), Error> {
+        self.stack_mut(to)?;
+        let pile = self.take(from, count)?;
+        let target = self.stack_mut(to)?;
+        match lift {
+            _ if from == to || lift >= pile.len() => target.extend(pile),
+            0 | 1 => target.extend(pile.into_iter().rev()),
+            _ => {
+                let mut pile = pile;
+                while !pile.is_empty() {
+                    let at = pile.len().saturating_sub(lift);
+                    target.extend(pile.drain(at..));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The puzzle's crate diagram, padded to full width, with a footer labelling the stacks.
+/// Columns widen to fit the longest crate name or label. Parsing the output gives back the
+/// same state.
+impl Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.width();
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(c) => format!("{:<1$}", format!("[{c}]"), width + 2),
+                    None => " ".repeat(width + 2),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let footer: Vec<String> = self
+            .labels
+            .iter()
+            .map(|l| format!(" {l:<0$}", width + 1))
+            .collect();
+        write!(f, "{}", footer.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Crane, CrateMover9000, Error, Stack, State};
+
+    const DIAGRAM: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 ";
+
+    #[test]
+    fn round_trip() {
+        let state = State::parse(&mut DIAGRAM.lines()).unwrap();
+        assert_eq!(state.to_string(), DIAGRAM);
+        let mut moved = state.clone();
+        let cmd = "move 3 from 2 to 1".parse().unwrap();
+        CrateMover9000.execute(&mut moved, &cmd).unwrap();
+        assert_eq!(
+            moved.to_string(),
+            "[M]        \n[C]        \n[D]        \n[N]        \n[Z]     [P]\n 1   2   3 "
+        );
+        assert_eq!(State::parse(&mut moved.to_string().lines()), Ok(moved));
+        assert_eq!(State::default().to_string(), "");
+        assert_eq!(State::parse(&mut "".lines()), Ok(State::default()));
+    }
+
+    #[test]
+    fn labels() {
+        let named = DIAGRAM.replace(" 1   2   3 ", " a   b   c ");
+        let state = State::parse(&mut named.lines()).unwrap();
+        assert_eq!(state.labels(), ["a", "b", "c"]);
+        assert_eq!(state.to_string(), named);
+        assert_eq!(state.number(&Stack::Name("c".to_string())), Ok(3));
+        assert_eq!(state.number(&Stack::Number(7)), Ok(7));
+        assert_eq!(
+            state.number(&Stack::Name("d".to_string())),
+            Err(Error::UnknownStack("d".to_string()))
+        );
+        assert_eq!(
+            State::new(state.stacks().to_vec()).labels(),
+            ["1", "2", "3"]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Command, Crane, Error, State};
+
+/// The top crate of every stack after `commands`, as [`State::peek`] would show it. Only the
+/// stack heights are run forwards; each final top is then followed back through the commands
+/// to the crate it started as, so no crate is ever moved and the time depends on the number of
+/// commands rather than how many crates they shift.
+pub fn final_tops(state: &State, commands: &[Command], crane: &dyn Crane) -> Result<String, Error> {
+    let mut heights: Vec<usize> = state.stacks().iter().map(|s| s.len()).collect();
+    for cmd in commands {
+        for s in [cmd.from, cmd.to] {
+            if !(1..=heights.len()).contains(&s) {
+                return Err(Error::Underflow);
+            }
+        }
+        if heights[cmd.from - 1] < cmd.count {
+            return Err(Error::Underflow);
+        }
+        heights[cmd.from - 1] -= cmd.count;
+        heights[cmd.to - 1] += cmd.count;
+    }
+    let mut tops = String::with_capacity(heights.len());
+    for (i, height) in heights.iter().enumerate() {
+        if *height == 0 {
+            tops.push(' ');
+            continue;
+        }
+        let (s, depth) = commands
+            .iter()
+            .rev()
+            .fold((i + 1, 0), |(s, depth), cmd| crane.before(cmd, s, depth));
+        let stack = &state.stacks()[s - 1];
+        tops.push_str(&stack[stack.len() - 1 - depth]);
+    }
+    Ok(tops)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{crane, final_tops, random::Random, Command, Error, State};
+
+    fn example() -> State {
+        State::new(vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']])
+    }
+
+    #[test]
+    fn example_tops() {
+        let commands: Vec<Command> = [
+            "move 1 from 2 to 1",
+            "move 3 from 1 to 3",
+            "move 2 from 2 to 1",
+            "move 1 from 1 to 2",
+        ]
+        .iter()
+        .map(|l| l.parse().unwrap())
+        .collect();
+        let tops = |name| final_tops(&example(), &commands, crane(name).unwrap().as_ref());
+        assert_eq!(tops("9000"), Ok("CMZ".to_string()));
+        assert_eq!(tops("9001"), Ok("MCD".to_string()));
+        assert_eq!(tops("adjacent:9000"), Ok("CMD".to_string()));
+        let crane = crane("9000").unwrap();
+        assert_eq!(
+            final_tops(&example(), &commands[1..], crane.as_ref()),
+            Err(Error::Underflow)
+        );
+    }
+
+    #[test]
+    fn matches_simulation() {
+        let mut random = Random::new(11);
+        let stacks: Vec<Vec<char>> = (0..6)
+            .map(|_| (0..30).map(|_| random.letter()).collect())
+            .collect();
+        let start = State::new(stacks);
+        let mut heights = [30; 6];
+        let mut commands = vec![];
+        while commands.len() < 300 {
+            let (from, to) = (random.below(6), random.below(6));
+            let count = random.below(heights[from] + 1);
+            heights[from] -= count;
+            heights[to] += count;
+            commands.push(Command {
+                from: from + 1,
+                to: to + 1,
+                count,
+            });
+        }
+        for name in [
+            "9000",
+            "9001",
+            "batch:1",
+            "batch:4",
+            "adjacent:9000",
+            "adjacent:batch:3",
+        ] {
+            let crane = crane(name).unwrap();
+            let mut state = start.clone();
+            for (i, cmd) in commands.iter().enumerate() {
+                crane.execute(&mut state, cmd).unwrap();
+                let tops = final_tops(&start, &commands[..=i], crane.as_ref());
+                assert_eq!(tops, Ok(state.peek()), "{name} after {}", i + 1);
+            }
+        }
+    }
+}
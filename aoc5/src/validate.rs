@@ -0,0 +1,183 @@
+use std::fmt::Display;
+
+use crate::{Instruction, Stack, State};
+
+/// Something wrong with one command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    Unparsable(String),
+    ZeroIndex,
+    NoSuchStack(usize),
+    UnknownStack(String),
+    SameStack(usize),
+    TooFewCrates {
+        stack: usize,
+        available: usize,
+        needed: usize,
+    },
+}
+
+/// A problem and the input line it is on, counting from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub line: usize,
+    pub problem: Problem,
+}
+
+/// The number of a stack that exists, or `None` after noting why not.
+fn resolve(state: &State, stack: &Stack, problems: &mut Vec<Problem>) -> Option<usize> {
+    let problem = match state.number(stack) {
+        Ok(0) => Problem::ZeroIndex,
+        Ok(s) if s > state.stacks().len() => Problem::NoSuchStack(s),
+        Ok(s) => return Some(s),
+        Err(_) => Problem::UnknownStack(stack.to_string()),
+    };
+    if !problems.contains(&problem) {
+        problems.push(problem);
+    }
+    None
+}
+
+/// Checks one instruction, updating the heights if it is valid.
+fn check(state: &State, heights: &mut [usize], instruction: &Instruction) -> Vec<Problem> {
+    let mut problems = vec![];
+    match instruction {
+        Instruction::Move { count, from, to } => {
+            let from = resolve(state, from, &mut problems);
+            let to: Vec<Option<usize>> = to
+                .iter()
+                .map(|s| resolve(state, s, &mut problems))
+                .collect();
+            let Some(from) = from else {
+                return problems;
+            };
+            if to.contains(&Some(from)) {
+                problems.push(Problem::SameStack(from));
+            }
+            let needed = count.unwrap_or(heights[from - 1]);
+            if heights[from - 1] < needed {
+                problems.push(Problem::TooFewCrates {
+                    stack: from,
+                    available: heights[from - 1],
+                    needed,
+                });
+            }
+            if problems.is_empty() {
+                heights[from - 1] -= needed;
+                let targets = to.len();
+                for (i, to) in to.into_iter().flatten().enumerate() {
+                    heights[to - 1] += needed / targets + usize::from(i < needed % targets);
+                }
+            }
+        }
+        Instruction::Swap(a, b) => {
+            let a = resolve(state, a, &mut problems);
+            let b = resolve(state, b, &mut problems);
+            if let (Some(a), Some(b)) = (a, b) {
+                heights.swap(a - 1, b - 1);
+            }
+        }
+        Instruction::Reverse(s) | Instruction::Rotate(s, _) => {
+            resolve(state, s, &mut problems);
+        }
+    }
+    problems
+}
+
+/// Checks every numbered instruction line against the heights of `state`'s stacks, which is
+/// all any crane model needs to know. Invalid instructions are reported and then skipped, so
+/// later lines are checked as if they had never been there.
+pub fn validate<T: AsRef<str>>(
+    state: &State,
+    lines: impl Iterator<Item = (usize, T)>,
+) -> Vec<Issue> {
+    let mut heights: Vec<usize> = state.stacks().iter().map(|s| s.len()).collect();
+    let mut issues = vec![];
+    for (line, text) in lines {
+        let problems = match Instruction::parse_line(text.as_ref()) {
+            Err(_) => vec![Problem::Unparsable(text.as_ref().to_string())],
+            Ok(None) => vec![],
+            Ok(Some(instruction)) => check(state, &mut heights, &instruction),
+        };
+        issues.extend(problems.into_iter().map(|problem| Issue { line, problem }));
+    }
+    issues
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Unparsable(text) => write!(f, "`{text}` is not a valid instruction"),
+            Problem::ZeroIndex => write!(f, "stacks are numbered from 1"),
+            Problem::NoSuchStack(s) => write!(f, "there is no stack {s}"),
+            Problem::UnknownStack(name) => write!(f, "no stack is labelled `{name}`"),
+            Problem::SameStack(s) => write!(f, "moves stack {s} onto itself"),
+            Problem::TooFewCrates {
+                stack,
+                available,
+                needed,
+            } => write!(
+                f,
+                "needs {needed} crates but stack {stack} only has {available}"
+            ),
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.problem)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{validate, Issue, Problem, State};
+
+    #[test]
+    fn problems() {
+        let state = State::new(vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]);
+        let before = state.clone();
+        let lines = [
+            "move 1 from 2 to 1",
+            "move 1 from 0 to 1",
+            "move 4 from 1 to 3",
+            "move 3 from 1 to 3",
+            "move 2 from 4 to 0",
+            "move 1 from 3 to 3",
+            "shuffle",
+            "move 5 from 3 to 2",
+            "",
+            "# deal the rest out",
+            "move all from 3 to 1,2 # four crates",
+            "swap 1 x",
+            "move 3 from 2 to 1,2",
+            "move 4 from 1 to 2",
+        ];
+        let issues = validate(&state, lines.iter().enumerate().map(|(i, l)| (i + 1, l)));
+        assert_eq!(state, before);
+        let messages: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 2: stacks are numbered from 1",
+                "line 3: needs 4 crates but stack 1 only has 3",
+                "line 5: there is no stack 4",
+                "line 5: stacks are numbered from 1",
+                "line 6: moves stack 3 onto itself",
+                "line 7: `shuffle` is not a valid instruction",
+                "line 8: needs 5 crates but stack 3 only has 4",
+                "line 12: no stack is labelled `x`",
+                "line 13: moves stack 2 onto itself",
+                "line 14: needs 4 crates but stack 1 only has 2",
+            ]
+        );
+        assert_eq!(
+            issues.first(),
+            Some(&Issue {
+                line: 2,
+                problem: Problem::ZeroIndex
+            })
+        );
+    }
+}
@@ -1,139 +1,114 @@
-use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(Debug)]
-enum Error {
-    Underflow,
-    InvalidCommand,
-}
+use aoc5::{
+    crane, final_tops, frames, plan, play, run, validate, Command, Instruction, Session, State,
+    Target,
+};
+
+/// The puzzle's crane, used unless `--crane` picks another.
+const DEFAULT: &str = "9001";
 
-struct State {
-    stacks: Vec<Vec<char>>,
+/// The argument following `flag`, if given.
+fn value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    let i = args.iter().position(|a| a == flag)?;
+    Some(args.get(i + 1).expect("Missing flag value"))
 }
 
-struct Command {
-    from: usize,
-    to: usize,
-    count: usize,
+/// The instructions as plain moves, for the modes that only understand those.
+fn plain(instructions: &[Instruction], state: &State) -> Vec<Command> {
+    instructions
+        .iter()
+        .map(|i| {
+            i.command(state)
+                .expect("Only plain moves are supported here")
+        })
+        .collect()
 }
 
-impl State {
-    fn parse(lines: &mut impl Iterator<Item = impl Into<String>>) -> Self {
-        let mut data: Vec<Vec<char>> = vec![];
-        while let Some(line) = lines.next() {
-            let line: String = line.into();
-            if line.trim().len() == 0 {
-                break;
-            }
-            data.push(line.chars().collect());
-        }
-        let mut stacks = vec![];
-        if data.len() == 0 {
-            return Self { stacks };
-        }
-        for (i, c) in data.last().unwrap().iter().enumerate() {
-            if !c.is_alphanumeric() {
-                continue;
-            }
-            let mut stack = vec![];
-            for l in data.iter().rev().skip(1) {
-                let Some(c2) = l.get(i) else {
-                    break;
-                };
-                if c2.is_alphanumeric() {
-                    stack.push(*c2);
-                }
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let crane = crane(value(&args, "--crane").unwrap_or(DEFAULT)).expect("Invalid crane");
+    if let Some(i) = args.iter().position(|a| a == "--repl") {
+        let state = match args.get(i + 1).filter(|a| !a.starts_with("--")) {
+            Some(file) => {
+                let diagram = std::fs::read_to_string(file).expect("Can't read diagram");
+                State::parse(&mut diagram.lines()).expect("Invalid diagram")
             }
-            stacks.push(stack);
-        }
-        Self { stacks }
+            None => State::default(),
+        };
+        let mut session = Session::new(state, crane);
+        run(&mut session, std::io::stdin().lock(), std::io::stdout()).expect("Session failed");
+        return;
     }
-
-    fn peek(&self) -> String {
-        let mut result = String::with_capacity(self.stacks.len());
-        for stack in &self.stacks {
-            result.push(*stack.last().unwrap_or(&' '));
+    let mut numbered = std::io::stdin()
+        .lines()
+        .map(|l| l.expect("Can't read line."))
+        .enumerate();
+    let mut state = State::parse(&mut numbered.by_ref().map(|(_, l)| l)).expect("Invalid diagram");
+    let lines: Vec<(usize, String)> = numbered.map(|(i, l)| (i + 1, l)).collect();
+    let issues = validate(&state, lines.iter().map(|(i, l)| (*i, l)));
+    issues.iter().for_each(|issue| eprintln!("{issue}"));
+    if !issues.is_empty() {
+        std::process::exit(1);
+    }
+    let target = match (value(&args, "--plan"), value(&args, "--plan-diagram")) {
+        (Some(tops), _) => Some(Target::Tops(tops.to_string())),
+        (None, Some(file)) => {
+            let diagram = std::fs::read_to_string(file).expect("Can't read target diagram");
+            let target = State::parse(&mut diagram.lines()).expect("Invalid target diagram");
+            Some(Target::Diagram(target))
         }
-        result
+        (None, None) => None,
+    };
+    if let Some(target) = target {
+        let limit =
+            value(&args, "--limit").map_or(1_000_000, |n| n.parse().expect("Invalid limit"));
+        let commands = plan(&state, &target, crane.as_ref(), limit).expect("No plan found");
+        commands.iter().for_each(|cmd| println!("{cmd}"));
+        return;
     }
-
-    fn pop_from(&mut self, s: usize) -> Option<char> {
-        self.stacks.get_mut(s)?.pop()
+    if args.iter().any(|a| a == "--check") {
+        println!("No problems found");
+        return;
     }
-
-    fn push_to(&mut self, s: usize, c: char) -> Result<(), Error> {
-        match self.stacks.get_mut(s) {
-            Some(stack) => Ok(stack.push(c)),
-            None => Err(Error::Underflow),
-        }
+    let instructions: Vec<Instruction> = lines
+        .iter()
+        .filter_map(|(_, l)| Instruction::parse_line(l).expect("Invalid Command"))
+        .collect();
+    if args.iter().any(|a| a == "--lazy") {
+        let tops = final_tops(&state, &plain(&instructions, &state), crane.as_ref())
+            .expect("Command could not execute");
+        println!("{tops}");
+        return;
     }
-
-    fn execute(&mut self, cmd: Command) -> Result<(), Error> {
-        let mut buffer = vec![];
-        for _i in 0..cmd.count {
-            let Some(c) = self.pop_from(cmd.from - 1) else {
-                return Err(Error::Underflow);
-            };
-            buffer.push(c);
-        }
-        for c in buffer.iter().rev() {
-            self.push_to(cmd.to - 1, *c)?;
+    if args.iter().any(|a| a == "--frames" || a == "--animate") {
+        let commands = plain(&instructions, &state);
+        let frames = frames(&state, &commands, crane.as_ref()).expect("Command could not execute");
+        match args.iter().any(|a| a == "--animate") {
+            true => {
+                let speed =
+                    value(&args, "--speed").map_or(300, |ms| ms.parse().expect("Invalid speed"));
+                play(&frames, Duration::from_millis(speed)).expect("Couldn't animate");
+            }
+            false => println!("{}", frames.join("\n\n")),
         }
-        Ok(())
+        return;
     }
-}
-
-fn expect<'a>(words: &mut impl Iterator<Item = &'a str>, word: &'static str) -> Result<(), Error> {
-    let Some(next) = words.next() else {
-        return Err(Error::InvalidCommand);
-    };
-    if !next.eq_ignore_ascii_case(word) {
-        return Err(Error::InvalidCommand);
+    for instruction in &instructions {
+        instruction
+            .execute(&mut state, crane.as_ref())
+            .expect("Command could not execute");
     }
-    Ok(())
-}
-
-fn get_value<'a>(words: &mut impl Iterator<Item = &'a str>) -> Result<usize, Error> {
-    words
-        .next()
-        .unwrap_or("")
-        .parse()
-        .map_err(|_| Error::InvalidCommand)
-}
-
-impl FromStr for Command {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut words = s.split_ascii_whitespace();
-        expect(&mut words, "move")?;
-        let count = get_value(&mut words)?;
-        expect(&mut words, "from")?;
-        let from = get_value(&mut words)?;
-        expect(&mut words, "to")?;
-        let to = get_value(&mut words)?;
-        match words.next() {
-            None => Ok(Self { from, to, count }),
-            _ => Err(Error::InvalidCommand),
-        }
+    if args.iter().any(|a| a == "--diagram") {
+        println!("{state}\n");
     }
-}
-
-fn main() {
-    let mut lines = std::io::stdin()
-        .lines()
-        .map(|l| l.expect("Can't read line."));
-    let mut state = State::parse(&mut lines);
-    lines.for_each(|l| {
-        state
-            .execute(l.parse().expect("Invalid Command"))
-            .expect("Command could not execute")
-    });
     println!("{}", state.peek());
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{Command, State};
+    use crate::DEFAULT;
+    use aoc5::{crane, Command, State};
 
     #[test]
     fn example() {
@@ -146,33 +121,41 @@ move 1 from 2 to 1
 move 3 from 1 to 3
 move 2 from 2 to 1
 move 1 from 1 to 2"#
-            .lines()
-            .into_iter();
-        let mut state = State::parse(&mut data);
+            .lines();
+        let crane = crane(DEFAULT).expect("Invalid crane");
+        let mut state = State::parse(&mut data).expect("Invalid diagram");
         assert_eq!(state.peek(), "NDP");
         let cmd: Command = data.next().unwrap().parse().expect("Invalid Command");
         assert_eq!(cmd.count, 1);
         assert_eq!(cmd.from, 2);
         assert_eq!(cmd.to, 1);
-        state.execute(cmd).expect("Command did not execute");
+        crane
+            .execute(&mut state, &cmd)
+            .expect("Command did not execute");
         assert_eq!(state.peek(), "DCP");
         let cmd: Command = data.next().unwrap().parse().expect("Invalid Command");
         assert_eq!(cmd.count, 3);
         assert_eq!(cmd.from, 1);
         assert_eq!(cmd.to, 3);
-        state.execute(cmd).expect("Command did not execute");
+        crane
+            .execute(&mut state, &cmd)
+            .expect("Command did not execute");
         assert_eq!(state.peek(), " CD");
         let cmd: Command = data.next().unwrap().parse().expect("Invalid Command");
         assert_eq!(cmd.count, 2);
         assert_eq!(cmd.from, 2);
         assert_eq!(cmd.to, 1);
-        state.execute(cmd).expect("Command did not execute");
+        crane
+            .execute(&mut state, &cmd)
+            .expect("Command did not execute");
         assert_eq!(state.peek(), "C D");
         let cmd: Command = data.next().unwrap().parse().expect("Invalid Command");
         assert_eq!(cmd.count, 1);
         assert_eq!(cmd.from, 1);
         assert_eq!(cmd.to, 2);
-        state.execute(cmd).expect("Command did not execute");
+        crane
+            .execute(&mut state, &cmd)
+            .expect("Command did not execute");
         assert_eq!(state.peek(), "MCD");
     }
 }